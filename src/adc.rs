@@ -0,0 +1,206 @@
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::auth::{
+    ADWORDS_SCOPE, AccessToken, AuthConfig, ServiceAccountKey, TokenResponse, cache_token, exchange_token,
+    refresh_access_token, service_account_token, token_cache,
+};
+use crate::paths::home_dir;
+
+const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+const TOKEN_EXCHANGE_GRANT: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+const DEFAULT_IMPERSONATION_LIFETIME_SECS: u64 = 3600;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AdcFile {
+    AuthorizedUser {
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+    },
+    ServiceAccount(ServiceAccountKey),
+    ExternalAccount(ExternalAccount),
+}
+
+#[derive(Debug, Deserialize)]
+struct ExternalAccount {
+    audience: String,
+    subject_token_type: String,
+    token_url: String,
+    credential_source: CredentialSource,
+    #[serde(default)]
+    service_account_impersonation_url: Option<String>,
+    #[serde(default)]
+    service_account_impersonation: Option<Impersonation>,
+    #[serde(default)]
+    workforce_pool_user_project: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CredentialSource {
+    #[serde(default)]
+    file: Option<String>,
+    #[serde(default)]
+    format: Option<SourceFormat>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SourceFormat {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    subject_token_field_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Impersonation {
+    #[serde(default)]
+    token_lifetime_seconds: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImpersonatedToken {
+    access_token: String,
+}
+
+pub fn credentials_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("GOOGLE_APPLICATION_CREDENTIALS").filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let gcloud = match env::var_os("CLOUDSDK_CONFIG").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(env::var_os("APPDATA")?).join("gcloud"),
+        None => home_dir().ok()?.join(".config").join("gcloud"),
+    };
+    Some(gcloud.join("application_default_credentials.json")).filter(|p| p.exists())
+}
+
+pub fn describe() -> Option<String> {
+    let path = credentials_path()?;
+    let kind = fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+        .and_then(|value| value.get("type").and_then(|t| t.as_str()).map(str::to_string))
+        .unwrap_or_else(|| "unknown".to_string());
+    Some(format!("application default credentials {} ({kind})", path.display()))
+}
+
+pub async fn access_token(config: &AuthConfig) -> Result<Option<AccessToken>> {
+    let Some(path) = credentials_path() else {
+        return Ok(None);
+    };
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("read application default credentials {}", path.display()))?;
+    let file: AdcFile = serde_json::from_str(&contents)
+        .with_context(|| format!("decode application default credentials {}", path.display()))?;
+    log::debug!("using application default credentials from {}", path.display());
+
+    let token = match file {
+        AdcFile::AuthorizedUser {
+            client_id,
+            client_secret,
+            refresh_token,
+        } => refresh_access_token(config, &client_id, &client_secret, &refresh_token).await?,
+        AdcFile::ServiceAccount(key) => service_account_token(config, &key).await?,
+        AdcFile::ExternalAccount(account) => external_account_token(config, &account).await?,
+    };
+    Ok(Some(token))
+}
+
+async fn external_account_token(config: &AuthConfig, account: &ExternalAccount) -> Result<AccessToken> {
+    let subject_token = read_subject_token(&account.credential_source)?;
+    let cache = token_cache(config, &account.token_url, &account.audience, &subject_token)?;
+    if let Some(token) = cache.as_ref().and_then(|c| c.load()) {
+        return Ok(token);
+    }
+
+    let impersonation_url = account.service_account_impersonation_url.as_deref();
+    let scope = if impersonation_url.is_some() {
+        CLOUD_PLATFORM_SCOPE
+    } else {
+        ADWORDS_SCOPE
+    };
+    let options = account
+        .workforce_pool_user_project
+        .as_ref()
+        .filter(|_| impersonation_url.is_none())
+        .map(|project| json!({ "userProject": project }).to_string());
+    let mut form = vec![
+        ("grant_type", TOKEN_EXCHANGE_GRANT),
+        ("audience", account.audience.as_str()),
+        ("scope", scope),
+        ("requested_token_type", ACCESS_TOKEN_TYPE),
+        ("subject_token", subject_token.as_str()),
+        ("subject_token_type", account.subject_token_type.as_str()),
+    ];
+    if let Some(options) = options.as_deref() {
+        form.push(("options", options));
+    }
+    let federated = exchange_token(&config.http, &account.token_url, &form).await?;
+
+    let Some(url) = impersonation_url else {
+        return Ok(cache_token(cache, federated));
+    };
+    let lifetime = account
+        .service_account_impersonation
+        .as_ref()
+        .and_then(|i| i.token_lifetime_seconds)
+        .unwrap_or(DEFAULT_IMPERSONATION_LIFETIME_SECS);
+    let resp = config
+        .http
+        .post(url)
+        .bearer_auth(&federated.access_token)
+        .json(&json!({ "scope": [ADWORDS_SCOPE], "lifetime": format!("{lifetime}s") }))
+        .send()
+        .await
+        .context("request impersonated service account token")?;
+    let status = resp.status();
+    let body = resp.text().await.context("read impersonation response")?;
+    if !status.is_success() {
+        return Err(anyhow!("impersonation http {}: {}", status, body));
+    }
+    let token: ImpersonatedToken = serde_json::from_str(&body).context("decode impersonation response")?;
+    Ok(cache_token(
+        cache,
+        TokenResponse {
+            access_token: token.access_token,
+            expires_in: Some(lifetime),
+            refresh_token: None,
+        },
+    ))
+}
+
+fn read_subject_token(source: &CredentialSource) -> Result<String> {
+    let path = source
+        .file
+        .as_ref()
+        .ok_or_else(|| anyhow!("only file-sourced external_account credentials are supported"))?;
+    let contents = fs::read_to_string(path).with_context(|| format!("read subject token {path}"))?;
+    let token = match source.format.as_ref() {
+        Some(format) if format.kind == "json" => {
+            let field = format
+                .subject_token_field_name
+                .as_deref()
+                .ok_or_else(|| anyhow!("credential_source.format.subject_token_field_name missing"))?;
+            let value: Value = serde_json::from_str(&contents)
+                .with_context(|| format!("decode subject token {path}"))?;
+            value
+                .get(field)
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("subject token field {field} missing in {path}"))?
+                .to_string()
+        }
+        _ => contents.trim().to_string(),
+    };
+    if token.is_empty() {
+        return Err(anyhow!("subject token file {path} is empty"));
+    }
+    Ok(token)
+}
@@ -1,53 +1,295 @@
 use anyhow::{Context, Result, anyhow};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
 use reqwest::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tokio::sync::Mutex;
 
-#[derive(Debug, Clone)]
+use crate::adc;
+use crate::redact;
+use crate::token_cache::{REFRESH_MARGIN_SECS, TokenCache, now};
+
+#[derive(Clone)]
 pub struct AuthConfig {
     pub access_token: Option<String>,
     pub client_id: Option<String>,
     pub client_secret: Option<String>,
     pub refresh_token: Option<String>,
+    pub service_account_file: Option<String>,
+    pub impersonated_email: Option<String>,
+    pub token_url: Option<String>,
+    pub http: Client,
+    pub use_cache: bool,
+    pub force_refresh: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct AccessToken {
+    pub token: String,
+    pub expires_at: Option<u64>,
+}
+
+pub struct TokenProvider {
+    config: AuthConfig,
+    current: Mutex<AccessToken>,
+}
+
+pub const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+pub const ADWORDS_SCOPE: &str = "https://www.googleapis.com/auth/adwords";
+const JWT_BEARER_GRANT: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+const ASSERTION_LIFETIME_SECS: u64 = 3600;
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    #[serde(default)]
+    private_key_id: Option<String>,
+    #[serde(default)]
+    token_uri: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub: Option<&'a str>,
 }
 
 #[derive(Debug, Deserialize)]
-struct TokenResponse {
-    access_token: String,
+pub struct TokenResponse {
+    pub access_token: String,
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
 }
 
 pub fn normalize_customer_id(value: &str) -> String {
     value.chars().filter(|c| c.is_ascii_digit()).collect()
 }
 
-pub async fn resolve_access_token(config: &AuthConfig) -> Result<String> {
-    if let Some(token) = config.access_token.as_ref() {
-        if !token.trim().is_empty() {
-            return Ok(token.trim().to_string());
+impl AuthConfig {
+    pub fn token_url(&self) -> &str {
+        self.token_url.as_deref().unwrap_or(TOKEN_URL)
+    }
+}
+
+impl TokenProvider {
+    pub async fn new(config: AuthConfig) -> Result<Self> {
+        let token = resolve_access_token(&config).await?;
+        Ok(Self {
+            config,
+            current: Mutex::new(token),
+        })
+    }
+
+    pub async fn token(&self) -> Result<String> {
+        let mut current = self.current.lock().await;
+        if current
+            .expires_at
+            .is_some_and(|at| at <= now() + REFRESH_MARGIN_SECS)
+        {
+            log::debug!("access token near expiry, refreshing");
+            *current = resolve_access_token(&self.config).await?;
+        }
+        Ok(current.token.clone())
+    }
+
+    pub async fn expires_at(&self) -> Option<u64> {
+        self.current.lock().await.expires_at
+    }
+
+    pub async fn refresh(&self, rejected: &str) -> Result<bool> {
+        if static_token(&self.config).is_some() {
+            return Ok(false);
         }
+        let mut current = self.current.lock().await;
+        if current.token == rejected {
+            log::warn!("access token rejected, refreshing");
+            let config = AuthConfig {
+                force_refresh: true,
+                ..self.config.clone()
+            };
+            *current = resolve_access_token(&config).await?;
+        }
+        Ok(true)
+    }
+}
+
+fn static_token(config: &AuthConfig) -> Option<&str> {
+    config
+        .access_token
+        .as_deref()
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+pub fn credential_source(config: &AuthConfig) -> Option<String> {
+    if static_token(config).is_some() {
+        return Some("access token".to_string());
+    }
+    if let Some(path) = config.service_account_file.as_ref() {
+        return Some(match config.impersonated_email.as_ref() {
+            Some(email) => format!("service account key {path} impersonating {email}"),
+            None => format!("service account key {path}"),
+        });
+    }
+    if let (Some(client_id), Some(_), Some(_)) = (
+        config.client_id.as_ref(),
+        config.client_secret.as_ref(),
+        config.refresh_token.as_ref(),
+    ) {
+        return Some(format!("OAuth refresh token for client {client_id}"));
+    }
+    adc::describe()
+}
+
+pub async fn resolve_access_token(config: &AuthConfig) -> Result<AccessToken> {
+    if let Some(token) = static_token(config) {
+        return Ok(AccessToken {
+            token: token.to_string(),
+            expires_at: None,
+        });
+    }
+    if let Some(path) = config.service_account_file.as_ref() {
+        let key = read_service_account_key(path)?;
+        return service_account_token(config, &key).await;
+    }
+
+    if let (Some(client_id), Some(client_secret), Some(refresh_token)) = (
+        config.client_id.as_ref(),
+        config.client_secret.as_ref(),
+        config.refresh_token.as_ref(),
+    ) {
+        return refresh_access_token(config, client_id, client_secret, refresh_token).await;
+    }
+
+    if let Some(token) = adc::access_token(config).await? {
+        return Ok(token);
     }
 
-    let client_id = config
-        .client_id
-        .as_ref()
-        .ok_or_else(|| anyhow!("GOOGLE_ADS_CLIENT_ID missing"))?;
-    let client_secret = config
-        .client_secret
-        .as_ref()
-        .ok_or_else(|| anyhow!("GOOGLE_ADS_CLIENT_SECRET missing"))?;
-    let refresh_token = config
-        .refresh_token
-        .as_ref()
-        .ok_or_else(|| anyhow!("GOOGLE_ADS_REFRESH_TOKEN missing"))?;
-
-    let client = Client::new();
-    let resp = client
-        .post("https://oauth2.googleapis.com/token")
-        .form(&[
+    if config.client_id.is_none() {
+        return Err(anyhow!("GOOGLE_ADS_CLIENT_ID missing"));
+    }
+    if config.client_secret.is_none() {
+        return Err(anyhow!("GOOGLE_ADS_CLIENT_SECRET missing"));
+    }
+    Err(anyhow!("GOOGLE_ADS_REFRESH_TOKEN missing"))
+}
+
+pub async fn refresh_access_token(
+    config: &AuthConfig,
+    client_id: &str,
+    client_secret: &str,
+    refresh_token: &str,
+) -> Result<AccessToken> {
+    let cache = token_cache(config, config.token_url(), client_id, refresh_token)?;
+    if let Some(token) = cache.as_ref().and_then(|c| c.load()) {
+        return Ok(token);
+    }
+
+    let token = exchange_token(
+        &config.http,
+        config.token_url(),
+        &[
             ("grant_type", "refresh_token"),
-            ("client_id", client_id.as_str()),
-            ("client_secret", client_secret.as_str()),
-            ("refresh_token", refresh_token.as_str()),
-        ])
+            ("client_id", client_id),
+            ("client_secret", client_secret),
+            ("refresh_token", refresh_token),
+        ],
+    )
+    .await?;
+    Ok(cache_token(cache, token))
+}
+
+fn read_service_account_key(path: &str) -> Result<ServiceAccountKey> {
+    let contents = fs::read_to_string(path).with_context(|| format!("read service account key {path}"))?;
+    serde_json::from_str(&contents).with_context(|| format!("decode service account key {path}"))
+}
+
+pub async fn service_account_token(config: &AuthConfig, key: &ServiceAccountKey) -> Result<AccessToken> {
+    let subject = config
+        .impersonated_email
+        .as_deref()
+        .filter(|email| !email.trim().is_empty());
+
+    let cache_key = format!(
+        "{}:{}",
+        key.private_key_id.as_deref().unwrap_or_default(),
+        subject.unwrap_or_default()
+    );
+    let token_uri = config
+        .token_url
+        .as_deref()
+        .or(key.token_uri.as_deref())
+        .unwrap_or(TOKEN_URL);
+    let cache = token_cache(config, token_uri, &key.client_email, &cache_key)?;
+    if let Some(token) = cache.as_ref().and_then(|c| c.load()) {
+        return Ok(token);
+    }
+
+    let assertion = sign_assertion(key, token_uri, subject)?;
+    let token = exchange_token(
+        &config.http,
+        token_uri,
+        &[("grant_type", JWT_BEARER_GRANT), ("assertion", assertion.as_str())],
+    )
+    .await?;
+    Ok(cache_token(cache, token))
+}
+
+fn sign_assertion(key: &ServiceAccountKey, audience: &str, subject: Option<&str>) -> Result<String> {
+    let iat = now();
+    let claims = Claims {
+        iss: &key.client_email,
+        scope: ADWORDS_SCOPE,
+        aud: audience,
+        iat,
+        exp: iat + ASSERTION_LIFETIME_SECS,
+        sub: subject,
+    };
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = key.private_key_id.clone();
+    let encoding_key =
+        EncodingKey::from_rsa_pem(key.private_key.as_bytes()).context("parse service account private key")?;
+    jsonwebtoken::encode(&header, &claims, &encoding_key).context("sign service account assertion")
+}
+
+pub fn token_cache(
+    config: &AuthConfig,
+    token_url: &str,
+    principal: &str,
+    secret: &str,
+) -> Result<Option<TokenCache>> {
+    if !config.use_cache {
+        return Ok(None);
+    }
+    Ok(Some(TokenCache::new(token_url, principal, secret)?.reuse(!config.force_refresh)))
+}
+
+pub fn cache_token(cache: Option<TokenCache>, token: TokenResponse) -> AccessToken {
+    redact::register(&token.access_token);
+    let expires_at = token.expires_in.map(|secs| now() + secs);
+    if let (Some(cache), Some(expires_at)) = (cache.as_ref(), expires_at)
+        && let Err(err) = cache.store(&token.access_token, expires_at)
+    {
+        log::warn!("failed to cache access token: {err:#}");
+    }
+    AccessToken {
+        token: token.access_token,
+        expires_at,
+    }
+}
+
+pub async fn exchange_token(http: &Client, url: &str, form: &[(&str, &str)]) -> Result<TokenResponse> {
+    let resp = http
+        .post(url)
+        .form(form)
         .send()
         .await
         .context("request OAuth token")?;
@@ -58,6 +300,5 @@ pub async fn resolve_access_token(config: &AuthConfig) -> Result<String> {
         return Err(anyhow!("oauth http {}: {}", status, body));
     }
 
-    let token: TokenResponse = serde_json::from_str(&body).context("decode OAuth response")?;
-    Ok(token.access_token)
+    serde_json::from_str(&body).context("decode OAuth response")
 }
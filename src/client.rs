@@ -2,17 +2,26 @@ use anyhow::{Result, anyhow};
 use bytes::Buf;
 use prost::Message;
 use prost_reflect::{DynamicMessage, MessageDescriptor, MethodDescriptor};
+use std::future::Future;
+use std::sync::Arc;
 use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
 use tonic::metadata::MetadataValue;
-use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
-use tonic::{Request, Status};
+use tonic::transport::Channel;
+use tonic::{Code, Request, Response, Status};
+
+use crate::auth::TokenProvider;
+use crate::retry::ServicePolicies;
+use crate::transport::TransportSettings;
+use crate::wire_log::{Entry, ResponseStream, WireLog};
 
 pub struct AdsClient {
     channel: Channel,
     developer_token: String,
     login_customer_id: Option<String>,
-    access_token: String,
+    tokens: TokenProvider,
     timeout: Option<std::time::Duration>,
+    policies: ServicePolicies,
+    wire_log: Option<Arc<WireLog>>,
 }
 
 impl AdsClient {
@@ -20,75 +29,122 @@ impl AdsClient {
         endpoint: &str,
         developer_token: String,
         login_customer_id: Option<String>,
-        access_token: String,
+        tokens: TokenProvider,
         timeout: Option<std::time::Duration>,
+        policies: ServicePolicies,
+        transport: &TransportSettings,
     ) -> Result<Self> {
         let endpoint = normalize_endpoint(endpoint)?;
-        let mut builder = Endpoint::from_shared(endpoint.clone())?;
-        if endpoint.starts_with("https://") {
-            builder = builder
-                .tls_config(ClientTlsConfig::new().with_webpki_roots())
-                .map_err(|err| anyhow!("tls config error: {err}"))?;
-        }
+        let mut builder = transport.configure_endpoint(&endpoint)?;
         if let Some(timeout) = timeout {
             builder = builder.timeout(timeout);
         }
-        let channel = builder.connect().await?;
+        let channel = transport.connect(builder).await?;
         Ok(Self {
             channel,
             developer_token,
             login_customer_id,
-            access_token,
+            tokens,
             timeout,
+            policies,
+            wire_log: None,
         })
     }
 
+    pub fn with_wire_log(mut self, wire_log: Option<WireLog>) -> Self {
+        self.wire_log = wire_log.map(Arc::new);
+        self
+    }
+
     pub async fn unary(&self, method: &MethodDescriptor, message: DynamicMessage) -> Result<DynamicMessage> {
         let path = method_path(method);
-        let codec = DynamicCodec::new(method.input(), method.output());
-        let mut grpc = tonic::client::Grpc::new(self.channel.clone());
-        grpc.ready()
-            .await
-            .map_err(|err| anyhow!("grpc not ready: {err}"))?;
-        let request = self.build_request(message)?;
-        let response = grpc.unary(request, path, codec).await?;
+        let (response, entry) = self.with_retry(method, message, |mut grpc, request| {
+            let path = path.clone();
+            let codec = DynamicCodec::new(method.input(), method.output());
+            async move { grpc.unary(request, path, codec).await }
+        })
+        .await?;
+        if let (Some(log), Some(entry)) = (self.wire_log.as_ref(), entry) {
+            log.success(entry, response.metadata(), Some(response.get_ref()));
+        }
         Ok(response.into_inner())
     }
 
-    pub async fn server_stream(&self, method: &MethodDescriptor, message: DynamicMessage) -> Result<Vec<DynamicMessage>> {
+    pub async fn server_stream(
+        &self,
+        method: &MethodDescriptor,
+        message: DynamicMessage,
+    ) -> Result<ResponseStream> {
         let path = method_path(method);
-        let codec = DynamicCodec::new(method.input(), method.output());
-        let mut grpc = tonic::client::Grpc::new(self.channel.clone());
-        grpc.ready()
-            .await
-            .map_err(|err| anyhow!("grpc not ready: {err}"))?;
-        let request = self.build_request(message)?;
-        let mut stream = grpc.server_streaming(request, path, codec).await?.into_inner();
-
-        let mut out = Vec::new();
-        while let Some(msg) = stream.message().await? {
-            out.push(msg);
-        }
-        Ok(out)
+        let (response, entry) = self.with_retry(method, message, |mut grpc, request| {
+            let path = path.clone();
+            let codec = DynamicCodec::new(method.input(), method.output());
+            async move { grpc.server_streaming(request, path, codec).await }
+        })
+        .await?;
+        let log = self.wire_log.clone().zip(entry);
+        Ok(ResponseStream::new(response, log))
     }
 
-    pub async fn server_streaming_raw(
+    async fn with_retry<T, F, Fut>(
         &self,
         method: &MethodDescriptor,
         message: DynamicMessage,
-    ) -> Result<tonic::Streaming<DynamicMessage>> {
-        let path = method_path(method);
-        let codec = DynamicCodec::new(method.input(), method.output());
-        let mut grpc = tonic::client::Grpc::new(self.channel.clone());
-        grpc.ready()
-            .await
-            .map_err(|err| anyhow!("grpc not ready: {err}"))?;
-        let request = self.build_request(message)?;
-        let response = grpc.server_streaming(request, path, codec).await?;
-        Ok(response.into_inner())
+        call: F,
+    ) -> Result<(Response<T>, Option<Entry>)>
+    where
+        F: Fn(tonic::client::Grpc<Channel>, Request<DynamicMessage>) -> Fut,
+        Fut: Future<Output = Result<Response<T>, Status>>,
+    {
+        let policy = self.policies.for_method(method);
+        let timeout = self.timeout.or(policy.timeout);
+        let mut attempt = 1;
+        let mut replayed = false;
+        loop {
+            let mut grpc = tonic::client::Grpc::new(self.channel.clone());
+            grpc.ready()
+                .await
+                .map_err(|err| anyhow!("grpc not ready: {err}"))?;
+            let token = self.tokens.token().await?;
+            let request = self.build_request(message.clone(), &token, timeout)?;
+            let entry = self
+                .wire_log
+                .as_ref()
+                .map(|log| log.start(method, &request, attempt));
+            let status = match call(grpc, request).await {
+                Ok(response) => return Ok((response, entry)),
+                Err(status) => status,
+            };
+            if let (Some(log), Some(entry)) = (self.wire_log.as_ref(), entry) {
+                log.failure(entry, &status);
+            }
+            if status.code() == Code::Unauthenticated && !replayed {
+                if !self.tokens.refresh(&token).await? {
+                    return Err(status.into());
+                }
+                replayed = true;
+                continue;
+            }
+            let Some(delay) = policy.retry_delay(&status, attempt) else {
+                return Err(status.into());
+            };
+            log::warn!(
+                "{} failed with {:?} (attempt {attempt}), retrying in {:.1}s",
+                method.full_name(),
+                status.code(),
+                delay.as_secs_f64()
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
     }
 
-    fn build_request<T>(&self, message: T) -> Result<Request<T>> {
+    fn build_request<T>(
+        &self,
+        message: T,
+        access_token: &str,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<Request<T>> {
         let mut request = Request::new(message);
         let metadata = request.metadata_mut();
 
@@ -96,7 +152,7 @@ impl AdsClient {
             .map_err(|_| anyhow!("invalid developer token"))?;
         metadata.insert("developer-token", developer);
 
-        let auth_value = format!("Bearer {}", self.access_token.trim());
+        let auth_value = format!("Bearer {}", access_token.trim());
         let auth = MetadataValue::try_from(auth_value.as_str())
             .map_err(|_| anyhow!("invalid access token"))?;
         metadata.insert("authorization", auth);
@@ -107,7 +163,7 @@ impl AdsClient {
             metadata.insert("login-customer-id", login);
         }
 
-        if let Some(timeout) = self.timeout {
+        if let Some(timeout) = timeout {
             request.set_timeout(timeout);
         }
 
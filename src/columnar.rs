@@ -0,0 +1,360 @@
+use anyhow::{Context, Result, anyhow};
+use arrow_array::builder::{
+    ArrayBuilder, BooleanBuilder, Date32Builder, Float32Builder, Float64Builder, Int32Builder,
+    Int64Builder, ListBuilder, StringBuilder, StringDictionaryBuilder, UInt32Builder, UInt64Builder,
+};
+use arrow_array::types::Int32Type;
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use prost_reflect::{DescriptorPool, FieldDescriptor, Kind};
+use serde_json::Value;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::gaql::{CUSTOMER_ID_COLUMN, resolve_field, row_descriptor, table_columns};
+use crate::output::{RowSink, TableColumn, collect_values};
+
+const BATCH_ROWS: usize = 8192;
+const DATE_FIELDS: &[&str] = &[
+    "segments.date",
+    "segments.week",
+    "segments.month",
+    "segments.quarter",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnarFormat {
+    Parquet,
+    Arrow,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ScalarKind {
+    Int64,
+    UInt64,
+    Int32,
+    UInt32,
+    Float64,
+    Float32,
+    Boolean,
+    Utf8,
+    Date32,
+    Enum,
+}
+
+struct Column {
+    table: TableColumn,
+    kind: ScalarKind,
+    list: bool,
+    nullable: bool,
+    builder: Box<dyn ArrayBuilder>,
+}
+
+enum Writer {
+    Parquet(ArrowWriter<File>),
+    Arrow(FileWriter<File>),
+}
+
+pub struct ColumnarSink {
+    schema: SchemaRef,
+    columns: Vec<Column>,
+    writer: Option<Writer>,
+    pending: usize,
+}
+
+impl ColumnarSink {
+    pub fn create(
+        pool: &DescriptorPool,
+        query: &str,
+        customer_column: bool,
+        path: &Path,
+        format: ColumnarFormat,
+    ) -> Result<Self> {
+        let row = row_descriptor(pool)?;
+        let mut fields = Vec::new();
+        let mut columns = Vec::new();
+        for table in table_columns(pool, query, customer_column)? {
+            if customer_column && table.name == CUSTOMER_ID_COLUMN {
+                fields.push(Field::new(table.name.clone(), DataType::Int64, false));
+                columns.push(Column {
+                    builder: new_builder(ScalarKind::Int64, false),
+                    table,
+                    kind: ScalarKind::Int64,
+                    list: false,
+                    nullable: false,
+                });
+                continue;
+            }
+            let chain = resolve_field(&row, &table.name)
+                .ok_or_else(|| anyhow!("unknown field {} in GoogleAdsRow", table.name))?;
+            let leaf = chain.last().ok_or_else(|| anyhow!("empty field {}", table.name))?;
+            let kind = scalar_kind(&table.name, leaf);
+            let list = chain.iter().any(|f| f.is_list());
+            let nullable = !list && (leaf.supports_presence() || matches!(leaf.kind(), Kind::Message(_)));
+            let data_type = if list {
+                DataType::List(Arc::new(Field::new("item", element_type(kind), true)))
+            } else {
+                element_type(kind)
+            };
+            fields.push(Field::new(table.name.clone(), data_type, nullable));
+            columns.push(Column {
+                builder: new_builder(kind, list),
+                table,
+                kind,
+                list,
+                nullable,
+            });
+        }
+
+        let schema = Arc::new(Schema::new(fields));
+        let file = File::create(path).with_context(|| format!("create {}", path.display()))?;
+        let writer = match format {
+            ColumnarFormat::Parquet => {
+                let props = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                Writer::Parquet(ArrowWriter::try_new(file, schema.clone(), Some(props))?)
+            }
+            ColumnarFormat::Arrow => Writer::Arrow(FileWriter::try_new(file, &schema)?),
+        };
+
+        Ok(Self {
+            schema,
+            columns,
+            writer: Some(writer),
+            pending: 0,
+        })
+    }
+
+    fn write_batch(&mut self) -> Result<()> {
+        if self.pending == 0 {
+            return Ok(());
+        }
+        let arrays = self
+            .columns
+            .iter_mut()
+            .map(|c| c.builder.finish())
+            .collect::<Vec<ArrayRef>>();
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays)?;
+        match self.writer.as_mut() {
+            Some(Writer::Parquet(writer)) => writer.write(&batch)?,
+            Some(Writer::Arrow(writer)) => writer.write(&batch)?,
+            None => return Err(anyhow!("columnar writer already closed")),
+        }
+        self.pending = 0;
+        Ok(())
+    }
+}
+
+impl RowSink for ColumnarSink {
+    fn write_row(&mut self, row: &Value) -> Result<()> {
+        for column in &mut self.columns {
+            let mut values = Vec::new();
+            collect_values(row, &column.table.path, &mut values);
+            if column.list {
+                let list = column
+                    .builder
+                    .as_any_mut()
+                    .downcast_mut::<ListBuilder<Box<dyn ArrayBuilder>>>()
+                    .ok_or_else(|| anyhow!("list builder mismatch for {}", column.table.name))?;
+                for value in values {
+                    append_value(list.values().as_mut(), column.kind, Some(value), &column.table.name)?;
+                }
+                list.append(true);
+                continue;
+            }
+            let value = values.first().copied();
+            if value.is_none() && !column.nullable {
+                let default = Value::String(column.table.default.clone());
+                append_value(column.builder.as_mut(), column.kind, Some(&default), &column.table.name)?;
+            } else {
+                append_value(column.builder.as_mut(), column.kind, value, &column.table.name)?;
+            }
+        }
+        self.pending += 1;
+        if self.pending >= BATCH_ROWS {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.write_batch()?;
+        match self.writer.take() {
+            Some(Writer::Parquet(writer)) => {
+                writer.close()?;
+            }
+            Some(Writer::Arrow(mut writer)) => writer.finish()?,
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+fn scalar_kind(name: &str, field: &FieldDescriptor) -> ScalarKind {
+    if DATE_FIELDS.contains(&name) {
+        return ScalarKind::Date32;
+    }
+    match field.kind() {
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => ScalarKind::Int64,
+        Kind::Uint64 | Kind::Fixed64 => ScalarKind::UInt64,
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => ScalarKind::Int32,
+        Kind::Uint32 | Kind::Fixed32 => ScalarKind::UInt32,
+        Kind::Double => ScalarKind::Float64,
+        Kind::Float => ScalarKind::Float32,
+        Kind::Bool => ScalarKind::Boolean,
+        Kind::Enum(_) => ScalarKind::Enum,
+        Kind::String | Kind::Bytes | Kind::Message(_) => ScalarKind::Utf8,
+    }
+}
+
+fn element_type(kind: ScalarKind) -> DataType {
+    match kind {
+        ScalarKind::Int64 => DataType::Int64,
+        ScalarKind::UInt64 => DataType::UInt64,
+        ScalarKind::Int32 => DataType::Int32,
+        ScalarKind::UInt32 => DataType::UInt32,
+        ScalarKind::Float64 => DataType::Float64,
+        ScalarKind::Float32 => DataType::Float32,
+        ScalarKind::Boolean => DataType::Boolean,
+        ScalarKind::Utf8 => DataType::Utf8,
+        ScalarKind::Date32 => DataType::Date32,
+        ScalarKind::Enum => DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+    }
+}
+
+fn new_builder(kind: ScalarKind, list: bool) -> Box<dyn ArrayBuilder> {
+    let scalar: Box<dyn ArrayBuilder> = match kind {
+        ScalarKind::Int64 => Box::new(Int64Builder::new()),
+        ScalarKind::UInt64 => Box::new(UInt64Builder::new()),
+        ScalarKind::Int32 => Box::new(Int32Builder::new()),
+        ScalarKind::UInt32 => Box::new(UInt32Builder::new()),
+        ScalarKind::Float64 => Box::new(Float64Builder::new()),
+        ScalarKind::Float32 => Box::new(Float32Builder::new()),
+        ScalarKind::Boolean => Box::new(BooleanBuilder::new()),
+        ScalarKind::Utf8 => Box::new(StringBuilder::new()),
+        ScalarKind::Date32 => Box::new(Date32Builder::new()),
+        ScalarKind::Enum => Box::new(StringDictionaryBuilder::<Int32Type>::new()),
+    };
+    if list {
+        Box::new(ListBuilder::new(scalar))
+    } else {
+        scalar
+    }
+}
+
+fn append_value(builder: &mut dyn ArrayBuilder, kind: ScalarKind, value: Option<&Value>, name: &str) -> Result<()> {
+    let any = builder.as_any_mut();
+    let mismatch = || anyhow!("builder mismatch for {name}");
+    match kind {
+        ScalarKind::Int64 => any
+            .downcast_mut::<Int64Builder>()
+            .ok_or_else(mismatch)?
+            .append_option(value.map(|v| parse_number(v, name)).transpose()?),
+        ScalarKind::UInt64 => any
+            .downcast_mut::<UInt64Builder>()
+            .ok_or_else(mismatch)?
+            .append_option(value.map(|v| parse_number(v, name)).transpose()?),
+        ScalarKind::Int32 => any
+            .downcast_mut::<Int32Builder>()
+            .ok_or_else(mismatch)?
+            .append_option(value.map(|v| parse_number(v, name)).transpose()?),
+        ScalarKind::UInt32 => any
+            .downcast_mut::<UInt32Builder>()
+            .ok_or_else(mismatch)?
+            .append_option(value.map(|v| parse_number(v, name)).transpose()?),
+        ScalarKind::Float64 => any
+            .downcast_mut::<Float64Builder>()
+            .ok_or_else(mismatch)?
+            .append_option(value.map(|v| parse_float(v, name)).transpose()?),
+        ScalarKind::Float32 => any
+            .downcast_mut::<Float32Builder>()
+            .ok_or_else(mismatch)?
+            .append_option(value.map(|v| parse_float(v, name).map(|f| f as f32)).transpose()?),
+        ScalarKind::Boolean => any
+            .downcast_mut::<BooleanBuilder>()
+            .ok_or_else(mismatch)?
+            .append_option(value.map(|v| v.as_bool().unwrap_or_else(|| v.as_str() == Some("true")))),
+        ScalarKind::Date32 => any
+            .downcast_mut::<Date32Builder>()
+            .ok_or_else(mismatch)?
+            .append_option(value.map(|v| parse_date(v, name)).transpose()?),
+        ScalarKind::Utf8 => any
+            .downcast_mut::<StringBuilder>()
+            .ok_or_else(mismatch)?
+            .append_option(value.map(text)),
+        ScalarKind::Enum => {
+            let builder = any
+                .downcast_mut::<StringDictionaryBuilder<Int32Type>>()
+                .ok_or_else(mismatch)?;
+            match value {
+                Some(v) => {
+                    builder.append(text(v))?;
+                }
+                None => builder.append_null(),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_number<T: std::str::FromStr>(value: &Value, name: &str) -> Result<T> {
+    let raw = match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    raw.parse()
+        .map_err(|_| anyhow!("invalid integer {raw} for {name}"))
+}
+
+fn parse_float(value: &Value, name: &str) -> Result<f64> {
+    match value {
+        Value::Number(n) => n.as_f64().ok_or_else(|| anyhow!("invalid number for {name}")),
+        Value::String(s) => match s.as_str() {
+            "NaN" => Ok(f64::NAN),
+            "Infinity" => Ok(f64::INFINITY),
+            "-Infinity" => Ok(f64::NEG_INFINITY),
+            other => other
+                .parse()
+                .map_err(|_| anyhow!("invalid number {other} for {name}")),
+        },
+        other => Err(anyhow!("invalid number {other} for {name}")),
+    }
+}
+
+fn parse_date(value: &Value, name: &str) -> Result<i32> {
+    let raw = value
+        .as_str()
+        .ok_or_else(|| anyhow!("invalid date {value} for {name}"))?;
+    let mut parts = raw.splitn(3, '-').map(|p| p.parse::<i64>());
+    let (Some(Ok(y)), Some(Ok(m)), Some(Ok(d))) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(anyhow!("invalid date {raw} for {name}"));
+    };
+    Ok(days_from_civil(y, m, d) as i32)
+}
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
@@ -1,6 +1,12 @@
 use anyhow::{Result, anyhow};
-use prost_reflect::{Cardinality, DescriptorPool, FieldDescriptor, Kind, MethodDescriptor, ServiceDescriptor};
+use prost_reflect::{
+    Cardinality, DescriptorPool, DynamicMessage, EnumDescriptor, ExtensionDescriptor, FieldDescriptor, Kind,
+    MessageDescriptor, MethodDescriptor, OneofDescriptor, ServiceDescriptor, Syntax, Value,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::docs;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CommandTree {
@@ -13,6 +19,7 @@ pub struct CommandTree {
 pub struct ServiceDef {
     pub name: String,
     pub full_name: String,
+    pub comment: Option<String>,
     pub methods: Vec<MethodDef>,
 }
 
@@ -24,15 +31,20 @@ pub struct MethodDef {
     pub output_type: String,
     pub client_streaming: bool,
     pub server_streaming: bool,
+    pub comment: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct MethodDescription {
     pub service: String,
     pub method: String,
+    pub comment: Option<String>,
     pub input_type: String,
     pub output_type: String,
     pub fields: Vec<FieldDef>,
+    pub output_fields: Vec<FieldDef>,
+    pub messages: Vec<MessageDef>,
+    pub enums: Vec<EnumDef>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -42,11 +54,62 @@ pub struct FieldDef {
     pub cardinality: String,
     pub kind: String,
     pub type_name: Option<String>,
+    pub oneof: Option<String>,
+    pub behaviors: Vec<String>,
+    pub resource_reference: Option<ResourceReferenceDef>,
+    pub resource_pattern: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ResourceReferenceDef {
+    #[serde(rename = "type")]
+    pub resource_type: Option<String>,
+    pub child_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TypeDescription {
+    pub full_name: String,
+    pub messages: Vec<MessageDef>,
+    pub enums: Vec<EnumDef>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MessageDef {
+    pub full_name: String,
+    pub comment: Option<String>,
+    pub fields: Vec<FieldDef>,
+    pub oneofs: Vec<OneofDef>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OneofDef {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EnumDef {
+    pub full_name: String,
+    pub comment: Option<String>,
+    pub values: Vec<EnumValueDef>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EnumValueDef {
+    pub name: String,
+    pub number: i32,
+    pub comment: Option<String>,
 }
 
 const DESCRIPTOR_BYTES: &[u8] = include_bytes!("../schemas/googleads.desc");
 const GOOGLE_ADS_PREFIX: &str = "google.ads.googleads.";
 const SERVICES_SEGMENT: &str = ".services.";
+const FIELD_BEHAVIOR: &str = "google.api.field_behavior";
+const RESOURCE_REFERENCE: &str = "google.api.resource_reference";
+const RESOURCE: &str = "google.api.resource";
+const RESOURCE_DEFINITION: &str = "google.api.resource_definition";
 
 pub fn load_pool() -> DescriptorPool {
     DescriptorPool::decode(DESCRIPTOR_BYTES).expect("invalid googleads.desc")
@@ -69,6 +132,7 @@ pub fn build_tree(pool: &DescriptorPool) -> CommandTree {
                 output_type: method.output().full_name().to_string(),
                 client_streaming: method.is_client_streaming(),
                 server_streaming: method.is_server_streaming(),
+                comment: docs::owned(method.full_name()),
             });
         }
         methods.sort_by(|a, b| a.name.cmp(&b.name));
@@ -76,6 +140,7 @@ pub fn build_tree(pool: &DescriptorPool) -> CommandTree {
         services.push(ServiceDef {
             name: to_kebab(service.name()),
             full_name: service.full_name().to_string(),
+            comment: docs::owned(service.full_name()),
             methods,
         });
     }
@@ -89,25 +154,141 @@ pub fn build_tree(pool: &DescriptorPool) -> CommandTree {
     }
 }
 
-pub fn describe_method(pool: &DescriptorPool, service: &str, method: &str) -> Result<MethodDescription> {
+pub fn describe_method(pool: &DescriptorPool, service: &str, method: &str, depth: usize) -> Result<MethodDescription> {
     let method_desc = find_method(pool, service, method)?;
     let input = method_desc.input();
     let output = method_desc.output();
 
+    let mut types = TypeCollector::new(pool);
     let fields = input
         .fields()
-        .map(field_def)
+        .map(|field| types.annotations.field_def(&field))
+        .collect::<Vec<_>>();
+    let output_fields = output
+        .fields()
+        .map(|field| types.annotations.field_def(&field))
         .collect::<Vec<_>>();
 
+    types.seen.insert(input.full_name().to_string());
+    types.seen.insert(output.full_name().to_string());
+    let roots = field_types(&input).chain(field_types(&output)).collect();
+    types.collect(roots, depth);
+
     Ok(MethodDescription {
         service: method_desc.parent_service().full_name().to_string(),
         method: method_desc.name().to_string(),
+        comment: docs::owned(method_desc.full_name()),
         input_type: input.full_name().to_string(),
         output_type: output.full_name().to_string(),
         fields,
+        output_fields,
+        messages: types.messages,
+        enums: types.enums,
+    })
+}
+
+pub fn describe_type(pool: &DescriptorPool, full_name: &str, depth: usize) -> Result<TypeDescription> {
+    let full_name = full_name.trim_start_matches('.');
+    let root = if let Some(message) = pool.get_message_by_name(full_name) {
+        TypeRef::Message(message)
+    } else if let Some(e) = pool.get_enum_by_name(full_name) {
+        TypeRef::Enum(e)
+    } else {
+        return Err(anyhow!("unknown message or enum {full_name}"));
+    };
+    let mut types = TypeCollector::new(pool);
+    types.collect(vec![root], depth + 1);
+    Ok(TypeDescription {
+        full_name: full_name.to_string(),
+        messages: types.messages,
+        enums: types.enums,
     })
 }
 
+enum TypeRef {
+    Message(MessageDescriptor),
+    Enum(EnumDescriptor),
+}
+
+struct TypeCollector {
+    annotations: Annotations,
+    seen: HashSet<String>,
+    messages: Vec<MessageDef>,
+    enums: Vec<EnumDef>,
+}
+
+impl TypeCollector {
+    fn new(pool: &DescriptorPool) -> Self {
+        Self {
+            annotations: Annotations::new(pool),
+            seen: HashSet::new(),
+            messages: Vec::new(),
+            enums: Vec::new(),
+        }
+    }
+
+    fn collect(&mut self, roots: Vec<TypeRef>, levels: usize) {
+        let mut frontier = roots;
+        for _ in 0..levels {
+            let mut next = Vec::new();
+            for ty in frontier {
+                match ty {
+                    TypeRef::Message(message) => {
+                        if !self.seen.insert(message.full_name().to_string()) {
+                            continue;
+                        }
+                        next.extend(field_types(&message));
+                        self.messages.push(self.annotations.message_def(&message));
+                    }
+                    TypeRef::Enum(e) => {
+                        if !self.seen.insert(e.full_name().to_string()) {
+                            continue;
+                        }
+                        self.enums.push(enum_def(&e));
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+    }
+}
+
+fn field_types(message: &MessageDescriptor) -> impl Iterator<Item = TypeRef> + use<> {
+    message
+        .fields()
+        .filter_map(|field| match field.kind() {
+            Kind::Message(m) => Some(TypeRef::Message(m)),
+            Kind::Enum(e) => Some(TypeRef::Enum(e)),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+pub fn is_real_oneof(oneof: &OneofDescriptor) -> bool {
+    !oneof
+        .fields()
+        .all(|field| field.field_descriptor_proto().proto3_optional())
+}
+
+fn enum_def(e: &EnumDescriptor) -> EnumDef {
+    EnumDef {
+        full_name: e.full_name().to_string(),
+        comment: docs::owned(e.full_name()),
+        values: e
+            .values()
+            .map(|v| EnumValueDef {
+                name: v.name().to_string(),
+                number: v.number(),
+                comment: docs::owned(&format!("{}.{}", e.full_name(), v.name())),
+            })
+            .collect(),
+    }
+}
+
 pub fn find_method(pool: &DescriptorPool, service: &str, method: &str) -> Result<MethodDescriptor> {
     let target_service = find_service(pool, service)?;
     let target_method = target_service
@@ -147,7 +328,7 @@ fn is_google_ads_service(service: &ServiceDescriptor) -> bool {
     service.full_name().starts_with(GOOGLE_ADS_PREFIX) && service.full_name().contains(SERVICES_SEGMENT)
 }
 
-fn detect_api_version(pool: &DescriptorPool) -> Option<String> {
+pub fn detect_api_version(pool: &DescriptorPool) -> Option<String> {
     for service in pool.services() {
         if !is_google_ads_service(&service) {
             continue;
@@ -161,23 +342,160 @@ fn detect_api_version(pool: &DescriptorPool) -> Option<String> {
     None
 }
 
-fn field_def(field: FieldDescriptor) -> FieldDef {
-    let kind = match field.kind() {
-        Kind::Message(m) => format!("message:{}", m.full_name()),
-        Kind::Enum(e) => format!("enum:{}", e.full_name()),
-        other => format!("scalar:{:?}", other).to_lowercase(),
+pub struct Annotations {
+    field_behavior: Option<ExtensionDescriptor>,
+    resource_reference: Option<ExtensionDescriptor>,
+    patterns: HashMap<String, String>,
+}
+
+impl Annotations {
+    pub fn new(pool: &DescriptorPool) -> Self {
+        let mut patterns = HashMap::new();
+        if let Some(resource) = pool.get_extension_by_name(RESOURCE) {
+            for message in pool.all_messages() {
+                let options = message.options();
+                if options.has_extension(&resource)
+                    && let Value::Message(descriptor) = options.get_extension(&resource).as_ref()
+                {
+                    add_pattern(&mut patterns, descriptor);
+                }
+            }
+        }
+        if let Some(definition) = pool.get_extension_by_name(RESOURCE_DEFINITION) {
+            for file in pool.files() {
+                let options = file.options();
+                if options.has_extension(&definition)
+                    && let Value::List(descriptors) = options.get_extension(&definition).as_ref()
+                {
+                    for descriptor in descriptors.iter().filter_map(Value::as_message) {
+                        add_pattern(&mut patterns, descriptor);
+                    }
+                }
+            }
+        }
+        Self {
+            field_behavior: pool.get_extension_by_name(FIELD_BEHAVIOR),
+            resource_reference: pool.get_extension_by_name(RESOURCE_REFERENCE),
+            patterns,
+        }
+    }
+
+    pub fn field_def(&self, field: &FieldDescriptor) -> FieldDef {
+        let kind = match field.kind() {
+            Kind::Message(m) => format!("message:{}", m.full_name()),
+            Kind::Enum(e) => format!("enum:{}", e.full_name()),
+            other => format!("scalar:{:?}", other).to_lowercase(),
+        };
+        let resource_reference = self.resource_reference(field);
+        let resource_pattern = resource_reference
+            .as_ref()
+            .and_then(|r| r.resource_type.as_ref())
+            .and_then(|t| self.patterns.get(t).cloned());
+
+        FieldDef {
+            name: field.name().to_string(),
+            json_name: field.json_name().to_string(),
+            cardinality: cardinality(field).to_string(),
+            kind,
+            type_name: type_name(field),
+            oneof: field
+                .containing_oneof()
+                .filter(is_real_oneof)
+                .map(|oneof| oneof.name().to_string()),
+            behaviors: self.behaviors(field),
+            resource_reference,
+            resource_pattern,
+            comment: docs::owned(field.full_name()),
+        }
+    }
+
+    pub fn message_def(&self, message: &MessageDescriptor) -> MessageDef {
+        MessageDef {
+            full_name: message.full_name().to_string(),
+            comment: docs::owned(message.full_name()),
+            fields: message.fields().map(|field| self.field_def(&field)).collect(),
+            oneofs: message
+                .oneofs()
+                .filter(is_real_oneof)
+                .map(|oneof| OneofDef {
+                    name: oneof.name().to_string(),
+                    fields: oneof.fields().map(|f| f.json_name().to_string()).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn behaviors(&self, field: &FieldDescriptor) -> Vec<String> {
+        let Some(ext) = self.field_behavior.as_ref() else {
+            return Vec::new();
+        };
+        let options = field.options();
+        if !options.has_extension(ext) {
+            return Vec::new();
+        }
+        let Kind::Enum(behavior) = ext.kind() else {
+            return Vec::new();
+        };
+        match options.get_extension(ext).as_ref() {
+            Value::List(values) => values
+                .iter()
+                .filter_map(Value::as_enum_number)
+                .map(|n| {
+                    behavior
+                        .get_value(n)
+                        .map_or_else(|| n.to_string(), |v| v.name().to_string())
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn resource_reference(&self, field: &FieldDescriptor) -> Option<ResourceReferenceDef> {
+        let ext = self.resource_reference.as_ref()?;
+        let options = field.options();
+        if !options.has_extension(ext) {
+            return None;
+        }
+        let Value::Message(reference) = options.get_extension(ext).into_owned() else {
+            return None;
+        };
+        Some(ResourceReferenceDef {
+            resource_type: string_option(&reference, "type"),
+            child_type: string_option(&reference, "child_type"),
+        })
+    }
+}
+
+fn add_pattern(patterns: &mut HashMap<String, String>, descriptor: &DynamicMessage) {
+    let Some(resource_type) = string_option(descriptor, "type") else {
+        return;
     };
+    let pattern = descriptor
+        .get_field_by_name("pattern")
+        .and_then(|v| v.as_list().and_then(|l| l.first()).and_then(|p| p.as_str().map(str::to_string)));
+    if let Some(pattern) = pattern {
+        patterns.entry(resource_type).or_insert(pattern);
+    }
+}
 
-    FieldDef {
-        name: field.name().to_string(),
-        json_name: field.json_name().to_string(),
-        cardinality: match field.cardinality() {
-            Cardinality::Optional => "optional".to_string(),
-            Cardinality::Required => "required".to_string(),
-            Cardinality::Repeated => "repeated".to_string(),
-        },
-        kind,
-        type_name: type_name(&field),
+fn string_option(message: &DynamicMessage, name: &str) -> Option<String> {
+    message
+        .get_field_by_name(name)
+        .and_then(|v| v.as_str().map(str::to_string))
+        .filter(|v| !v.is_empty())
+}
+
+fn cardinality(field: &FieldDescriptor) -> &'static str {
+    match field.cardinality() {
+        Cardinality::Repeated => "repeated",
+        Cardinality::Required => "required",
+        Cardinality::Optional
+            if field.parent_file().syntax() == Syntax::Proto2
+                || field.field_descriptor_proto().proto3_optional() =>
+        {
+            "optional"
+        }
+        Cardinality::Optional => "singular",
     }
 }
 
@@ -189,7 +507,7 @@ fn type_name(field: &FieldDescriptor) -> Option<String> {
     }
 }
 
-fn to_kebab(value: &str) -> String {
+pub fn to_kebab(value: &str) -> String {
     let mut out = String::new();
     for (i, ch) in value.chars().enumerate() {
         if ch.is_uppercase() {
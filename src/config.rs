@@ -0,0 +1,512 @@
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::auth::{AuthConfig, normalize_customer_id};
+use crate::credentials::StoredCredentials;
+use crate::paths::{config_dir, home_dir};
+use crate::redact;
+use crate::transport::TransportSettings;
+
+const DEFAULT_ENDPOINT: &str = "https://googleads.googleapis.com";
+const DEFAULT_PROFILE: &str = "default";
+
+static STDIN_READ: AtomicBool = AtomicBool::new(false);
+
+pub struct Config {
+    pub developer_token: Option<String>,
+    pub endpoint: String,
+    pub login_customer_id: Option<String>,
+    pub customer_id: Option<String>,
+    pub access_token: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub refresh_token: Option<String>,
+    pub service_account_file: Option<String>,
+    pub impersonated_email: Option<String>,
+    pub timeout: Option<Duration>,
+    pub token_url: Option<String>,
+    pub tokeninfo_url: Option<String>,
+    pub transport: TransportSettings,
+    pub profile: Option<String>,
+    pub settings: Vec<Setting>,
+}
+
+impl Config {
+    pub fn developer_token(&self) -> Result<String> {
+        self.developer_token
+            .clone()
+            .ok_or_else(|| anyhow!("GOOGLE_ADS_DEVELOPER_TOKEN missing"))
+    }
+
+    pub fn auth_config(&self, use_cache: bool) -> Result<AuthConfig> {
+        Ok(AuthConfig {
+            access_token: self.access_token.clone(),
+            client_id: self.client_id.clone(),
+            client_secret: self.client_secret.clone(),
+            refresh_token: self.refresh_token.clone(),
+            service_account_file: self.service_account_file.clone(),
+            impersonated_email: self.impersonated_email.clone(),
+            token_url: self.token_url.clone(),
+            http: self.transport.http_client()?,
+            use_cache,
+            force_refresh: false,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Setting {
+    pub name: &'static str,
+    pub value: String,
+    pub source: Source,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Source {
+    Flag { flag: String },
+    Env { var: String },
+    Profile { profile: String, path: PathBuf },
+    GoogleAdsYaml { path: PathBuf },
+    CredentialStore { path: PathBuf },
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Flag { flag } => write!(f, "flag {flag}"),
+            Source::Env { var } => write!(f, "env {var}"),
+            Source::Profile { profile, path } => write!(f, "profile {profile} ({})", path.display()),
+            Source::GoogleAdsYaml { path } => write!(f, "google-ads.yaml ({})", path.display()),
+            Source::CredentialStore { path } => write!(f, "credential store ({})", path.display()),
+            Source::Default => f.write_str("default"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileSettings {
+    #[serde(deserialize_with = "string_or_number")]
+    developer_token: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    client_id: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    client_secret: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    refresh_token: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    login_customer_id: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    customer_id: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    endpoint: Option<String>,
+    #[serde(alias = "service_account_file", deserialize_with = "string_or_number")]
+    json_key_file_path: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    impersonated_email: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    token_url: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    tokeninfo_url: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    ca_bundle: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    client_cert: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    client_key: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    authority: Option<String>,
+    #[serde(deserialize_with = "string_or_number")]
+    proxy: Option<String>,
+    timeout: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    default_profile: Option<String>,
+    profiles: BTreeMap<String, FileSettings>,
+}
+
+#[derive(Debug, Deserialize)]
+struct YamlFile {
+    #[serde(flatten)]
+    settings: FileSettings,
+    #[serde(flatten)]
+    _other: BTreeMap<String, serde_yaml::Value>,
+}
+
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Str(String),
+        Int(i64),
+    }
+    Ok(Option::<Raw>::deserialize(deserializer)?.map(|raw| match raw {
+        Raw::Str(s) => s,
+        Raw::Int(n) => n.to_string(),
+    }))
+}
+
+struct Layers<'a> {
+    matches: &'a clap::ArgMatches,
+    profile: Option<(String, PathBuf, FileSettings)>,
+    explicit_profile: bool,
+    yaml: Option<(PathBuf, FileSettings)>,
+    stored: Option<Option<(PathBuf, StoredCredentials)>>,
+    settings: Vec<Setting>,
+}
+
+impl Layers<'_> {
+    fn resolve(
+        &mut self,
+        name: &'static str,
+        env_var: &str,
+        file: fn(&FileSettings) -> Option<&String>,
+        stored: Option<fn(&StoredCredentials) -> &String>,
+    ) -> Result<Option<String>> {
+        let found = match self.flag(name)? {
+            Some(found) => Some(found),
+            None => {
+                let env = || {
+                    env::var(env_var)
+                        .ok()
+                        .filter(|v| !v.is_empty())
+                        .map(|value| (value, Source::Env { var: env_var.to_string() }))
+                };
+                let profile = || {
+                    let (profile, path, settings) = self.profile.as_ref()?;
+                    let source = Source::Profile {
+                        profile: profile.clone(),
+                        path: path.clone(),
+                    };
+                    file(settings).map(|value| (value.clone(), source))
+                };
+                let layered = if self.explicit_profile {
+                    profile().or_else(env)
+                } else {
+                    env().or_else(profile)
+                };
+                layered.or_else(|| {
+                    let (path, settings) = self.yaml.as_ref()?;
+                    file(settings).map(|value| (value.clone(), Source::GoogleAdsYaml { path: path.clone() }))
+                })
+            }
+        };
+        let found = match (found, stored) {
+            (Some(found), _) => Some(found),
+            (None, Some(get)) => self
+                .stored()?
+                .map(|(path, credentials)| (get(credentials).clone(), Source::CredentialStore { path: path.clone() })),
+            (None, None) => None,
+        };
+        let Some((value, source)) = found else {
+            return Ok(None);
+        };
+        if is_secret(name) {
+            redact::register(&value);
+        }
+        self.record(name, &value, source);
+        Ok(Some(value))
+    }
+
+    fn flag(&self, name: &str) -> Result<Option<(String, Source)>> {
+        if let Some(value) = self.matches.try_get_one::<String>(name).ok().flatten() {
+            let flag = format!("--{}", name.replace('_', "-"));
+            return Ok(Some((value.clone(), Source::Flag { flag })));
+        }
+        let Some(path) = self
+            .matches
+            .try_get_one::<String>(&format!("{name}_file"))
+            .ok()
+            .flatten()
+        else {
+            return Ok(None);
+        };
+        let flag = format!("--{}-file", name.replace('_', "-"));
+        let value = read_secret(path).with_context(|| format!("{flag} {path}"))?;
+        Ok(Some((value, Source::Flag { flag })))
+    }
+
+    fn stored(&mut self) -> Result<Option<&(PathBuf, StoredCredentials)>> {
+        if self.stored.is_none() {
+            let loaded = match StoredCredentials::load() {
+                Ok(Some(credentials)) => Some((StoredCredentials::path()?, credentials)),
+                Ok(None) => None,
+                Err(err) => {
+                    log::warn!("ignoring stored credentials: {err:#}");
+                    None
+                }
+            };
+            self.stored = Some(loaded);
+        }
+        Ok(self.stored.as_ref().and_then(Option::as_ref))
+    }
+
+    fn record(&mut self, name: &'static str, value: &str, source: Source) {
+        let value = if is_secret(name) { mask(value) } else { value.to_string() };
+        self.settings.push(Setting { name, value, source });
+    }
+}
+
+pub fn load_config(matches: &clap::ArgMatches) -> Result<Config> {
+    load_config_with_store(matches, true)
+}
+
+pub fn load_config_with_store(matches: &clap::ArgMatches, use_store: bool) -> Result<Config> {
+    let (toml_path, yaml_path) = config_paths(matches)?;
+    let profile_name = matches
+        .get_one::<String>("profile")
+        .cloned()
+        .or_else(|| env::var("SEARCH_ADS_PROFILE").ok().filter(|v| !v.is_empty()));
+
+    let profile = match toml_path {
+        Some(path) => select_profile(&path, profile_name.as_deref())?,
+        None if profile_name.is_some() => {
+            return Err(anyhow!(
+                "--profile given but no config file at {}",
+                config_dir()?.join("config.toml").display()
+            ));
+        }
+        None => None,
+    };
+    let yaml = match yaml_path {
+        Some(path) => {
+            let settings = read_yaml(&path)?;
+            Some((path, settings))
+        }
+        None => None,
+    };
+    let mut layers = Layers {
+        matches,
+        explicit_profile: profile_name.is_some(),
+        profile,
+        yaml,
+        stored: (!use_store).then_some(None),
+        settings: Vec::new(),
+    };
+
+    let developer_token = layers.resolve(
+        "developer_token",
+        "GOOGLE_ADS_DEVELOPER_TOKEN",
+        |s| s.developer_token.as_ref(),
+        None,
+    )?;
+    let access_token = layers.resolve("access_token", "GOOGLE_ADS_ACCESS_TOKEN", |_| None, None)?;
+    let service_account_file = layers.resolve(
+        "service_account_file",
+        "GOOGLE_ADS_JSON_KEY_FILE_PATH",
+        |s| s.json_key_file_path.as_ref(),
+        None,
+    )?;
+    let impersonated_email = layers.resolve(
+        "impersonated_email",
+        "GOOGLE_ADS_IMPERSONATED_EMAIL",
+        |s| s.impersonated_email.as_ref(),
+        None,
+    )?;
+    let refresh_flow =
+        access_token.as_deref().is_none_or(|t| t.trim().is_empty()) && service_account_file.is_none();
+    let stored = |get: fn(&StoredCredentials) -> &String| refresh_flow.then_some(get);
+    let client_id = layers.resolve(
+        "client_id",
+        "GOOGLE_ADS_CLIENT_ID",
+        |s| s.client_id.as_ref(),
+        stored(|c| &c.client_id),
+    )?;
+    let client_secret = layers.resolve(
+        "client_secret",
+        "GOOGLE_ADS_CLIENT_SECRET",
+        |s| s.client_secret.as_ref(),
+        stored(|c| &c.client_secret),
+    )?;
+    let refresh_token = layers.resolve(
+        "refresh_token",
+        "GOOGLE_ADS_REFRESH_TOKEN",
+        |s| s.refresh_token.as_ref(),
+        stored(|c| &c.refresh_token),
+    )?;
+    let login_customer_id = layers
+        .resolve(
+            "login_customer_id",
+            "GOOGLE_ADS_LOGIN_CUSTOMER_ID",
+            |s| s.login_customer_id.as_ref(),
+            None,
+        )?
+        .map(|value| normalize_customer_id(&value));
+    let customer_id = layers
+        .resolve(
+            "customer_id",
+            "GOOGLE_ADS_CUSTOMER_ID",
+            |s| s.customer_id.as_ref(),
+            None,
+        )?
+        .map(|value| normalize_customer_id(&value));
+    let endpoint = layers
+        .resolve("endpoint", "GOOGLE_ADS_ENDPOINT", |s| s.endpoint.as_ref(), None)?
+        .unwrap_or_else(|| {
+            layers.record("endpoint", DEFAULT_ENDPOINT, Source::Default);
+            DEFAULT_ENDPOINT.to_string()
+        });
+    let timeout = resolve_timeout(&mut layers);
+    let token_url = layers.resolve("token_url", "GOOGLE_ADS_TOKEN_URL", |s| s.token_url.as_ref(), None)?;
+    let tokeninfo_url = layers.resolve(
+        "tokeninfo_url",
+        "GOOGLE_ADS_TOKENINFO_URL",
+        |s| s.tokeninfo_url.as_ref(),
+        None,
+    )?;
+    let transport = TransportSettings {
+        ca_bundle: layers.resolve("ca_bundle", "GOOGLE_ADS_CA_BUNDLE", |s| s.ca_bundle.as_ref(), None)?,
+        client_cert: layers.resolve(
+            "client_cert",
+            "GOOGLE_ADS_CLIENT_CERT",
+            |s| s.client_cert.as_ref(),
+            None,
+        )?,
+        client_key: layers.resolve("client_key", "GOOGLE_ADS_CLIENT_KEY", |s| s.client_key.as_ref(), None)?,
+        authority: layers.resolve("authority", "GOOGLE_ADS_AUTHORITY", |s| s.authority.as_ref(), None)?,
+        proxy: layers.resolve("proxy", "GOOGLE_ADS_PROXY", |s| s.proxy.as_ref(), None)?,
+    };
+
+    for setting in &layers.settings {
+        log::debug!("{} = {} ({})", setting.name, setting.value, setting.source);
+    }
+
+    Ok(Config {
+        developer_token,
+        endpoint,
+        login_customer_id,
+        customer_id,
+        access_token,
+        client_id,
+        client_secret,
+        refresh_token,
+        service_account_file,
+        impersonated_email,
+        timeout,
+        token_url,
+        tokeninfo_url,
+        transport,
+        profile: layers.profile.as_ref().map(|(name, _, _)| name.clone()),
+        settings: layers.settings,
+    })
+}
+
+fn resolve_timeout(layers: &mut Layers<'_>) -> Option<Duration> {
+    let (secs, source) = match layers.matches.get_one::<u64>("timeout") {
+        Some(secs) => (*secs, Source::Flag { flag: "--timeout".to_string() }),
+        None => {
+            let (profile, path, settings) = layers.profile.as_ref()?;
+            let source = Source::Profile {
+                profile: profile.clone(),
+                path: path.clone(),
+            };
+            (settings.timeout?, source)
+        }
+    };
+    layers.record("timeout", &format!("{secs}s"), source);
+    Some(Duration::from_secs(secs))
+}
+
+fn config_paths(matches: &clap::ArgMatches) -> Result<(Option<PathBuf>, Option<PathBuf>)> {
+    let explicit = matches
+        .get_one::<String>("config")
+        .cloned()
+        .or_else(|| env::var("SEARCH_ADS_CONFIG").ok().filter(|v| !v.is_empty()))
+        .map(PathBuf::from);
+    if let Some(path) = explicit {
+        if !path.exists() {
+            return Err(anyhow!("config file {} not found", path.display()));
+        }
+        return Ok(if is_yaml(&path) { (None, Some(path)) } else { (Some(path), None) });
+    }
+
+    let toml = config_dir()?.join("config.toml");
+    let yaml = env::var("GOOGLE_ADS_CONFIGURATION_FILE_PATH")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().ok().map(|home| home.join("google-ads.yaml")));
+    Ok((
+        Some(toml).filter(|p| p.exists()),
+        yaml.filter(|p| p.exists()),
+    ))
+}
+
+fn select_profile(path: &Path, requested: Option<&str>) -> Result<Option<(String, PathBuf, FileSettings)>> {
+    let contents = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    let mut file: ConfigFile =
+        toml::from_str(&contents).with_context(|| format!("parse {}", path.display()))?;
+    let name = match requested.map(str::to_string).or(file.default_profile.clone()) {
+        Some(name) => name,
+        None if file.profiles.contains_key(DEFAULT_PROFILE) => DEFAULT_PROFILE.to_string(),
+        None => return Ok(None),
+    };
+    let settings = file.profiles.remove(&name).ok_or_else(|| {
+        let names = file.profiles.keys().cloned().collect::<Vec<_>>();
+        anyhow!(
+            "profile {name} not found in {} (available: {})",
+            path.display(),
+            names.join(", ")
+        )
+    })?;
+    Ok(Some((name, path.to_path_buf(), settings)))
+}
+
+fn read_yaml(path: &Path) -> Result<FileSettings> {
+    let contents = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    if contents.trim().is_empty() {
+        return Ok(FileSettings::default());
+    }
+    let file: YamlFile = serde_yaml::from_str(&contents).with_context(|| format!("parse {}", path.display()))?;
+    Ok(file.settings)
+}
+
+fn read_secret(path: &str) -> Result<String> {
+    let contents = if path == "-" {
+        if STDIN_READ.swap(true, Ordering::SeqCst) {
+            return Err(anyhow!("stdin can only supply one secret"));
+        }
+        let mut contents = String::new();
+        std::io::stdin().read_to_string(&mut contents).context("read stdin")?;
+        contents
+    } else {
+        fs::read_to_string(path).context("read secret file")?
+    };
+    let value = contents.trim();
+    if value.is_empty() {
+        return Err(anyhow!("secret is empty"));
+    }
+    Ok(value.to_string())
+}
+
+fn is_yaml(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("yaml") | Some("yml")
+    )
+}
+
+fn is_secret(name: &str) -> bool {
+    matches!(
+        name,
+        "developer_token" | "access_token" | "client_secret" | "refresh_token"
+    )
+}
+
+fn mask(value: &str) -> String {
+    let prefix: String = value.chars().take(4).collect();
+    format!("{prefix}…")
+}
@@ -0,0 +1,169 @@
+use anyhow::{Context, Result, anyhow};
+use argon2::Argon2;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+use crate::paths::{config_dir, create_private_dir, write_private};
+
+const KDF: &str = "argon2id";
+const CIPHER: &str = "xchacha20poly1305";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredFile {
+    Encrypted { encrypted: Sealed },
+    Plain(StoredCredentials),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Sealed {
+    kdf: String,
+    cipher: String,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl StoredCredentials {
+    pub fn path() -> Result<PathBuf> {
+        Ok(config_dir()?.join("credentials.json"))
+    }
+
+    pub fn load() -> Result<Option<Self>> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents =
+            fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+        let file: StoredFile = serde_json::from_str(&contents)
+            .with_context(|| format!("decode credentials {}", path.display()))?;
+        match file {
+            StoredFile::Plain(stored) => Ok(Some(stored)),
+            StoredFile::Encrypted { encrypted } => {
+                let passphrase = passphrase(&format!("Passphrase for {}: ", path.display()), false)?;
+                let plaintext = open(&encrypted, &passphrase)
+                    .with_context(|| format!("decrypt credentials {}", path.display()))?;
+                let stored = serde_json::from_slice(&plaintext)
+                    .with_context(|| format!("decode credentials {}", path.display()))?;
+                Ok(Some(stored))
+            }
+        }
+    }
+
+    pub fn save(&self, encrypt: bool) -> Result<PathBuf> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            create_private_dir(parent)?;
+        }
+        let file = if encrypt {
+            let passphrase = passphrase("New passphrase for the credential file: ", true)?;
+            StoredFile::Encrypted {
+                encrypted: seal(&serde_json::to_vec(self)?, &passphrase)?,
+            }
+        } else {
+            StoredFile::Plain(self.clone())
+        };
+        let tmp = path.with_extension("json.tmp");
+        write_private(&tmp, &serde_json::to_vec_pretty(&file)?)
+            .with_context(|| format!("write {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("write {}", path.display()))?;
+        Ok(path)
+    }
+
+    pub fn delete() -> Result<Option<PathBuf>> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
+        Ok(Some(path))
+    }
+}
+
+fn passphrase(prompt: &str, confirm: bool) -> Result<String> {
+    if let Ok(value) = env::var("SEARCH_ADS_PASSPHRASE")
+        && !value.is_empty()
+    {
+        return Ok(value);
+    }
+    if let Ok(path) = env::var("SEARCH_ADS_PASSPHRASE_FILE")
+        && !path.is_empty()
+    {
+        let value = fs::read_to_string(&path).with_context(|| format!("read passphrase file {path}"))?;
+        return Ok(value.trim_end_matches(['\r', '\n']).to_string());
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "credential file is encrypted; set SEARCH_ADS_PASSPHRASE or SEARCH_ADS_PASSPHRASE_FILE"
+        ));
+    }
+    let value = rpassword::prompt_password(prompt).context("read passphrase")?;
+    if value.is_empty() {
+        return Err(anyhow!("empty passphrase"));
+    }
+    if confirm && rpassword::prompt_password("Repeat passphrase: ").context("read passphrase")? != value {
+        return Err(anyhow!("passphrases do not match"));
+    }
+    Ok(value)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| anyhow!("derive key: {err}"))?;
+    Ok(key)
+}
+
+fn seal(plaintext: &[u8], passphrase: &str) -> Result<Sealed> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let key = derive_key(passphrase, &salt)?;
+    let ciphertext = XChaCha20Poly1305::new(&key.into())
+        .encrypt(XNonce::from_slice(&nonce), plaintext)
+        .map_err(|_| anyhow!("encrypt credentials"))?;
+    Ok(Sealed {
+        kdf: KDF.to_string(),
+        cipher: CIPHER.to_string(),
+        salt: STANDARD.encode(salt),
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    })
+}
+
+fn open(sealed: &Sealed, passphrase: &str) -> Result<Vec<u8>> {
+    if sealed.kdf != KDF || sealed.cipher != CIPHER {
+        return Err(anyhow!("unsupported encryption {}/{}", sealed.kdf, sealed.cipher));
+    }
+    let salt = STANDARD.decode(&sealed.salt).context("decode salt")?;
+    let nonce = STANDARD.decode(&sealed.nonce).context("decode nonce")?;
+    if nonce.len() != NONCE_LEN {
+        return Err(anyhow!("invalid nonce length"));
+    }
+    let ciphertext = STANDARD.decode(&sealed.ciphertext).context("decode ciphertext")?;
+    let key = derive_key(passphrase, &salt)?;
+    XChaCha20Poly1305::new(&key.into())
+        .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| anyhow!("wrong passphrase or corrupted file"))
+}
@@ -0,0 +1,101 @@
+use flate2::read::GzDecoder;
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::LazyLock;
+
+const DOCS_BYTES: &[u8] = include_bytes!("../schemas/googleads.docs.gz");
+
+static DOCS: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
+    let mut raw = String::new();
+    if let Err(err) = GzDecoder::new(DOCS_BYTES).read_to_string(&mut raw) {
+        log::warn!("failed to load proto docs: {err}");
+        return HashMap::new();
+    }
+    raw.lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(name, text)| (name.to_string(), unescape(text)))
+        .collect()
+});
+
+pub fn comment(full_name: &str) -> Option<&'static str> {
+    DOCS.get(full_name).map(String::as_str)
+}
+
+pub fn owned(full_name: &str) -> Option<String> {
+    comment(full_name).map(str::to_string)
+}
+
+pub fn summary(comment: &str) -> String {
+    comment
+        .split("\n\n")
+        .next()
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_tree::load_pool;
+    use prost_reflect::DescriptorPool;
+
+    fn resolves(pool: &DescriptorPool, name: &str) -> bool {
+        if pool.get_message_by_name(name).is_some()
+            || pool.get_enum_by_name(name).is_some()
+            || pool.get_service_by_name(name).is_some()
+        {
+            return true;
+        }
+        let Some((parent, child)) = name.rsplit_once('.') else {
+            return false;
+        };
+        pool.get_message_by_name(parent)
+            .is_some_and(|m| m.get_field_by_name(child).is_some())
+            || pool
+                .get_enum_by_name(parent)
+                .is_some_and(|e| e.get_value_by_name(child).is_some())
+            || pool
+                .get_service_by_name(parent)
+                .is_some_and(|s| s.methods().any(|m| m.name() == child))
+    }
+
+    #[test]
+    fn every_index_key_resolves_in_pool() {
+        let pool = load_pool();
+        assert!(DOCS.len() > 10_000);
+        let unresolved = DOCS.keys().filter(|name| !resolves(&pool, name)).collect::<Vec<_>>();
+        let sample = &unresolved[..unresolved.len().min(10)];
+        assert!(unresolved.is_empty(), "{} unresolved, e.g. {sample:?}", unresolved.len());
+    }
+
+    #[test]
+    fn strips_thrown_errors_boilerplate() {
+        let comment = comment("google.ads.googleads.v23.services.CampaignService.MutateCampaigns").unwrap();
+        assert_eq!(comment, "Creates, updates, or removes campaigns. Operation statuses are returned.");
+    }
+
+    #[test]
+    fn unescapes_and_summarizes() {
+        assert_eq!(unescape("a\\nb\\\\c"), "a\nb\\c");
+        assert_eq!(summary("First line\ncontinues.\n\nSecond paragraph."), "First line continues.");
+    }
+}
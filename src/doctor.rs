@@ -0,0 +1,305 @@
+use anyhow::Result;
+use prost_reflect::{DescriptorPool, DynamicMessage};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::auth::{ADWORDS_SCOPE, AuthConfig, TokenProvider, credential_source};
+use crate::client::AdsClient;
+use crate::command_tree::find_method;
+use crate::config::Config;
+use crate::errors::status_report;
+use crate::gaql;
+use crate::proto_json::dynamic_to_value;
+use crate::retry::ServicePolicies;
+use crate::token_cache::now;
+use crate::wire_log::WireLog;
+
+pub const TOKENINFO_URL: &str = "https://oauth2.googleapis.com/tokeninfo";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Ok => f.pad("ok"),
+            Status::Warn => f.pad("warn"),
+            Status::Fail => f.pad("fail"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub checks: Vec<Check>,
+    pub accessible_customers: Vec<String>,
+}
+
+impl Report {
+    fn push(&mut self, name: &'static str, status: Status, detail: impl Into<String>) {
+        self.checks.push(Check {
+            name,
+            status,
+            detail: detail.into(),
+        });
+    }
+
+    pub fn failures(&self) -> usize {
+        self.checks.iter().filter(|c| c.status == Status::Fail).count()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenInfo {
+    #[serde(default)]
+    scope: Option<String>,
+    #[serde(default)]
+    expires_in: Option<String>,
+    #[serde(default)]
+    email: Option<String>,
+}
+
+pub async fn run(
+    pool: &DescriptorPool,
+    config: &Config,
+    auth: AuthConfig,
+    customer_id: Option<String>,
+    tokeninfo_url: &str,
+    wire_log: Option<WireLog>,
+) -> Result<Report> {
+    let mut report = Report::default();
+
+    match credential_source(&auth) {
+        Some(source) => report.push("credentials", Status::Ok, source),
+        None => {
+            report.push(
+                "credentials",
+                Status::Fail,
+                "no credentials found; set GOOGLE_ADS_REFRESH_TOKEN (+ client id/secret), \
+                 GOOGLE_ADS_JSON_KEY_FILE_PATH, or run `search-ads auth login`",
+            );
+            return Ok(report);
+        }
+    }
+
+    let developer_token = config.developer_token.clone();
+    match developer_token.as_ref() {
+        Some(_) => report.push(
+            "developer_token",
+            Status::Ok,
+            format!("set ({})", setting_source(config, "developer_token")),
+        ),
+        None => report.push("developer_token", Status::Fail, "GOOGLE_ADS_DEVELOPER_TOKEN missing"),
+    }
+
+    let tokens = match TokenProvider::new(auth.clone()).await {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            report.push("access_token", Status::Fail, format!("{err:#}"));
+            return Ok(report);
+        }
+    };
+    let token = tokens.token().await?;
+    let expiry = match tokens.expires_at().await {
+        Some(at) => format!("expires in {}s", at.saturating_sub(now())),
+        None => "static token, expiry unknown".to_string(),
+    };
+    report.push("access_token", Status::Ok, expiry);
+    check_tokeninfo(&mut report, &auth, tokeninfo_url, &token).await;
+
+    let Some(developer_token) = developer_token else {
+        return Ok(report);
+    };
+    let policies = ServicePolicies::load()?;
+    let client = match AdsClient::connect(
+        &config.endpoint,
+        developer_token,
+        config.login_customer_id.clone(),
+        tokens,
+        config.timeout,
+        policies,
+        &config.transport,
+    )
+    .await
+    {
+        Ok(client) => client.with_wire_log(wire_log),
+        Err(err) => {
+            report.push("connect", Status::Fail, format!("{}: {err:#}", config.endpoint));
+            return Ok(report);
+        }
+    };
+
+    let method = find_method(pool, "customer-service", "list-accessible-customers")?;
+    let accessible = match client.unary(&method, DynamicMessage::new(method.input())).await {
+        Ok(response) => {
+            let value = dynamic_to_value(&response)?;
+            value
+                .get("resourceNames")
+                .and_then(|v| v.as_array())
+                .map(|names| {
+                    names
+                        .iter()
+                        .filter_map(|n| n.as_str())
+                        .map(|n| n.trim_start_matches("customers/").to_string())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        }
+        Err(err) => {
+            report.push("list_accessible_customers", Status::Fail, describe_error(pool, &err));
+            return Ok(report);
+        }
+    };
+    report.push(
+        "list_accessible_customers",
+        if accessible.is_empty() { Status::Warn } else { Status::Ok },
+        format!("{} accessible customer(s)", accessible.len()),
+    );
+    report.accessible_customers = accessible;
+
+    check_login_customer(&mut report, pool, &client, config, customer_id).await;
+    Ok(report)
+}
+
+async fn check_tokeninfo(report: &mut Report, auth: &AuthConfig, url: &str, token: &str) {
+    let resp = match auth.http.get(url).query(&[("access_token", token)]).send().await {
+        Ok(resp) => resp,
+        Err(err) => {
+            report.push("token_scopes", Status::Warn, format!("tokeninfo request failed: {err}"));
+            return;
+        }
+    };
+    let status = resp.status();
+    let body = resp.text().await.unwrap_or_default();
+    if !status.is_success() {
+        report.push("token_scopes", Status::Fail, format!("tokeninfo http {status}: {}", body.trim()));
+        return;
+    }
+    let info: TokenInfo = match serde_json::from_str(&body) {
+        Ok(info) => info,
+        Err(err) => {
+            report.push("token_scopes", Status::Warn, format!("decode tokeninfo response: {err}"));
+            return;
+        }
+    };
+    let scope = info.scope.unwrap_or_default();
+    let mut detail = scope.clone();
+    if let Some(email) = info.email.as_ref() {
+        detail.push_str(&format!(" (account {email})"));
+    }
+    if let Some(expires_in) = info.expires_in.as_ref() {
+        detail.push_str(&format!(" (expires in {expires_in}s)"));
+    }
+    if scope.split_whitespace().any(|s| s == ADWORDS_SCOPE) {
+        report.push("token_scopes", Status::Ok, detail);
+    } else {
+        report.push(
+            "token_scopes",
+            Status::Fail,
+            format!("token lacks {ADWORDS_SCOPE}; granted: {detail}"),
+        );
+    }
+}
+
+async fn check_login_customer(
+    report: &mut Report,
+    pool: &DescriptorPool,
+    client: &AdsClient,
+    config: &Config,
+    customer_id: Option<String>,
+) {
+    let accessible = &report.accessible_customers;
+    let target = customer_id.or_else(|| config.customer_id.clone());
+    let Some(login) = config.login_customer_id.clone() else {
+        match target {
+            Some(target) if !accessible.contains(&target) => report.push(
+                "login_customer_id",
+                Status::Warn,
+                format!(
+                    "not set, and customer {target} is not directly accessible; \
+                     set --login-customer-id to a manager account that links to it"
+                ),
+            ),
+            _ => report.push("login_customer_id", Status::Ok, "not set"),
+        }
+        return;
+    };
+
+    if accessible.contains(&login) {
+        report.push(
+            "login_customer_id",
+            Status::Ok,
+            format!("{login} ({}) is accessible", setting_source(config, "login_customer_id")),
+        );
+    } else {
+        report.push(
+            "login_customer_id",
+            Status::Fail,
+            format!(
+                "{login} ({}) is not among the customers these credentials can access: {}",
+                setting_source(config, "login_customer_id"),
+                accessible.join(", ")
+            ),
+        );
+    }
+
+    let Some(target) = target.filter(|target| target != &login) else {
+        return;
+    };
+    let query = format!(
+        "SELECT customer_client.id FROM customer_client WHERE customer_client.id = {target}"
+    );
+    match gaql::list_client_ids(client, pool, &login, Some(&query)).await {
+        Ok(ids) if ids.contains(&target) => {
+            report.push("manager_link", Status::Ok, format!("{login} manages {target}"))
+        }
+        Ok(_) => report.push(
+            "manager_link",
+            Status::Fail,
+            format!("{login} is not a manager of {target}; requests for {target} will be rejected"),
+        ),
+        Err(err) => report.push(
+            "manager_link",
+            Status::Fail,
+            format!("could not read the hierarchy of {login}: {}", describe_error(pool, &err)),
+        ),
+    }
+}
+
+fn setting_source(config: &Config, name: &str) -> String {
+    config
+        .settings
+        .iter()
+        .find(|s| s.name == name)
+        .map(|s| s.source.to_string())
+        .unwrap_or_else(|| "unset".to_string())
+}
+
+fn describe_error(pool: &DescriptorPool, err: &anyhow::Error) -> String {
+    let Some(status) = err.chain().find_map(|e| e.downcast_ref::<tonic::Status>()) else {
+        return format!("{err:#}");
+    };
+    let report = status_report(pool, status);
+    let codes = report
+        .errors
+        .iter()
+        .filter_map(|e| e.error_code.as_deref())
+        .collect::<Vec<_>>();
+    if codes.is_empty() {
+        format!("{}: {}", report.code, report.message)
+    } else {
+        format!("{}: {} ({})", report.code, codes.join(", "), report.message)
+    }
+}
@@ -0,0 +1,248 @@
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, ReflectMessage, Value as ProtoValue};
+use serde::Serialize;
+use serde_json::{Value, json};
+use tonic::{Code, Status};
+
+const RPC_STATUS: &str = "google.rpc.Status";
+const FAILURE_SUFFIX: &str = ".errors.GoogleAdsFailure";
+const FAILURE_TRAILER_SUFFIX: &str = ".errors.googleadsfailure-bin";
+
+#[derive(Debug, Serialize)]
+pub struct StatusReport {
+    pub code: String,
+    pub message: String,
+    pub request_id: Option<String>,
+    pub errors: Vec<AdsError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdsError {
+    pub error_code: Option<String>,
+    pub message: String,
+    pub trigger: Option<Value>,
+    pub field_path: Option<String>,
+    pub field_path_elements: Vec<FieldPathElement>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FieldPathElement {
+    pub field_name: String,
+    pub index: Option<i64>,
+}
+
+pub fn status_report(pool: &DescriptorPool, status: &Status) -> StatusReport {
+    let failure = failure_from_details(pool, status).or_else(|| failure_from_trailer(pool, status));
+    let mut request_id = header(status, "request-id");
+    let mut errors = Vec::new();
+
+    if let Some(failure) = failure {
+        if request_id.is_none() {
+            request_id = string_field(&failure, "request_id");
+        }
+        errors = repeated_messages(&failure, "errors")
+            .iter()
+            .map(ads_error)
+            .collect();
+    }
+
+    StatusReport {
+        code: code_name(status.code()).to_string(),
+        message: status.message().to_string(),
+        request_id,
+        errors,
+    }
+}
+
+impl StatusReport {
+    pub fn to_json(&self) -> Value {
+        json!(self)
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut out = vec![format!("grpc status {}: {}", self.code, self.message)];
+        if let Some(request_id) = self.request_id.as_ref() {
+            out.push(format!("request-id: {request_id}"));
+        }
+        for (idx, error) in self.errors.iter().enumerate() {
+            let code = error.error_code.as_deref().unwrap_or("UNKNOWN");
+            out.push(format!("  [{idx}] {code}: {}", error.message));
+            if let Some(trigger) = error.trigger.as_ref() {
+                out.push(format!("      trigger: {trigger}"));
+            }
+            if let Some(field_path) = error.field_path.as_ref() {
+                out.push(format!("      field: {field_path}"));
+            }
+        }
+        out
+    }
+}
+
+pub fn code_name(code: Code) -> &'static str {
+    match code {
+        Code::Ok => "OK",
+        Code::Cancelled => "CANCELLED",
+        Code::Unknown => "UNKNOWN",
+        Code::InvalidArgument => "INVALID_ARGUMENT",
+        Code::DeadlineExceeded => "DEADLINE_EXCEEDED",
+        Code::NotFound => "NOT_FOUND",
+        Code::AlreadyExists => "ALREADY_EXISTS",
+        Code::PermissionDenied => "PERMISSION_DENIED",
+        Code::ResourceExhausted => "RESOURCE_EXHAUSTED",
+        Code::FailedPrecondition => "FAILED_PRECONDITION",
+        Code::Aborted => "ABORTED",
+        Code::OutOfRange => "OUT_OF_RANGE",
+        Code::Unimplemented => "UNIMPLEMENTED",
+        Code::Internal => "INTERNAL",
+        Code::Unavailable => "UNAVAILABLE",
+        Code::DataLoss => "DATA_LOSS",
+        Code::Unauthenticated => "UNAUTHENTICATED",
+    }
+}
+
+fn failure_from_details(pool: &DescriptorPool, status: &Status) -> Option<DynamicMessage> {
+    if status.details().is_empty() {
+        return None;
+    }
+    let rpc_status = pool.get_message_by_name(RPC_STATUS)?;
+    let decoded = DynamicMessage::decode(rpc_status, status.details()).ok()?;
+    for any in repeated_messages(&decoded, "details") {
+        let type_url = string_field(&any, "type_url").unwrap_or_default();
+        let type_name = type_url.rsplit('/').next().unwrap_or_default();
+        if !type_name.ends_with(FAILURE_SUFFIX) {
+            continue;
+        }
+        let desc = pool.get_message_by_name(type_name)?;
+        let bytes = any.get_field_by_name("value")?.as_bytes()?.clone();
+        if let Ok(failure) = DynamicMessage::decode(desc, bytes) {
+            return Some(failure);
+        }
+    }
+    None
+}
+
+fn failure_from_trailer(pool: &DescriptorPool, status: &Status) -> Option<DynamicMessage> {
+    for key in status.metadata().keys() {
+        let tonic::metadata::KeyRef::Binary(key) = key else {
+            continue;
+        };
+        if !key.as_str().ends_with(FAILURE_TRAILER_SUFFIX) {
+            continue;
+        }
+        let bytes = status.metadata().get_bin(key.as_str())?.to_bytes().ok()?;
+        let desc = failure_descriptor(pool, key.as_str())?;
+        return DynamicMessage::decode(desc, bytes).ok();
+    }
+    None
+}
+
+fn failure_descriptor(pool: &DescriptorPool, trailer: &str) -> Option<MessageDescriptor> {
+    let package = trailer.strip_suffix(FAILURE_TRAILER_SUFFIX)?;
+    pool.get_message_by_name(&format!("{package}{FAILURE_SUFFIX}"))
+}
+
+fn header(status: &Status, key: &str) -> Option<String> {
+    status
+        .metadata()
+        .get(key)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+fn ads_error(error: &DynamicMessage) -> AdsError {
+    let field_path_elements = error
+        .get_field_by_name("location")
+        .and_then(|loc| loc.as_message().map(|m| repeated_messages(m, "field_path_elements")))
+        .unwrap_or_default()
+        .iter()
+        .map(|el| FieldPathElement {
+            field_name: string_field(el, "field_name").unwrap_or_default(),
+            index: el
+                .get_field_by_name("index")
+                .filter(|_| has_field(el, "index"))
+                .and_then(|v| v.as_i32())
+                .map(i64::from),
+        })
+        .collect::<Vec<_>>();
+
+    AdsError {
+        error_code: error
+            .get_field_by_name("error_code")
+            .and_then(|v| v.as_message().and_then(error_code_name)),
+        message: string_field(error, "message").unwrap_or_default(),
+        trigger: error
+            .get_field_by_name("trigger")
+            .filter(|_| has_field(error, "trigger"))
+            .and_then(|v| v.as_message().and_then(trigger_value)),
+        field_path: field_path(&field_path_elements),
+        field_path_elements,
+    }
+}
+
+fn error_code_name(code: &DynamicMessage) -> Option<String> {
+    let (field, value) = code.fields().next()?;
+    let ProtoValue::EnumNumber(number) = value else {
+        return None;
+    };
+    let prost_reflect::Kind::Enum(enum_desc) = field.kind() else {
+        return None;
+    };
+    let value_name = enum_desc
+        .get_value(*number)
+        .map(|v| v.name().to_string())
+        .unwrap_or_else(|| number.to_string());
+    Some(format!("{}.{}", enum_desc.name(), value_name))
+}
+
+fn trigger_value(trigger: &DynamicMessage) -> Option<Value> {
+    let (_, value) = trigger.fields().next()?;
+    let value = match value {
+        ProtoValue::Bool(v) => json!(v),
+        ProtoValue::I64(v) => json!(v.to_string()),
+        ProtoValue::F32(v) => json!(v),
+        ProtoValue::F64(v) => json!(v),
+        ProtoValue::String(v) => json!(v),
+        other => json!(format!("{other:?}")),
+    };
+    Some(value)
+}
+
+fn field_path(elements: &[FieldPathElement]) -> Option<String> {
+    if elements.is_empty() {
+        return None;
+    }
+    let mut out = String::new();
+    for element in elements {
+        if !out.is_empty() {
+            out.push('.');
+        }
+        out.push_str(&element.field_name);
+        if let Some(index) = element.index {
+            out.push_str(&format!("[{index}]"));
+        }
+    }
+    Some(out)
+}
+
+fn has_field(message: &DynamicMessage, name: &str) -> bool {
+    message
+        .descriptor()
+        .get_field_by_name(name)
+        .is_some_and(|field| message.has_field(&field))
+}
+
+fn string_field(message: &DynamicMessage, name: &str) -> Option<String> {
+    message
+        .get_field_by_name(name)
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .filter(|s| !s.is_empty())
+}
+
+fn repeated_messages(message: &DynamicMessage, name: &str) -> Vec<DynamicMessage> {
+    message
+        .get_field_by_name(name)
+        .and_then(|v| {
+            v.as_list()
+                .map(|items| items.iter().filter_map(|i| i.as_message().cloned()).collect())
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,204 @@
+use anyhow::{Context, Result, anyhow};
+use prost_reflect::DescriptorPool;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::client::AdsClient;
+use crate::command_tree::find_method;
+use crate::gaql_parser::{Diagnostic, Query, Severity, diagnostic};
+use crate::paths::cache_dir;
+use crate::proto_json::{dynamic_from_value, dynamic_to_value};
+
+const CACHE_VERSION: u32 = 1;
+const SERVICE: &str = "google-ads-field-service";
+const SEARCH_FIELDS: &str = "search-google-ads-fields";
+const FIELDS_QUERY: &str = "SELECT name, category, data_type, selectable, filterable, sortable, \
+     selectable_with, attribute_resources, metrics, segments, enum_values, type_url, is_repeated";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldCache {
+    pub version: u32,
+    pub api_version: String,
+    pub synced_at: u64,
+    pub fields: BTreeMap<String, FieldMeta>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FieldMeta {
+    pub name: String,
+    pub category: String,
+    pub data_type: String,
+    pub selectable: bool,
+    pub filterable: bool,
+    pub sortable: bool,
+    pub is_repeated: bool,
+    pub type_url: Option<String>,
+    pub enum_values: Vec<String>,
+    pub selectable_with: Vec<String>,
+    pub attribute_resources: Vec<String>,
+    pub metrics: Vec<String>,
+    pub segments: Vec<String>,
+}
+
+impl FieldCache {
+    pub fn path(api_version: &str) -> Result<PathBuf> {
+        Ok(cache_dir()?.join(format!("fields-{api_version}.json")))
+    }
+
+    pub fn load(api_version: &str) -> Result<Option<Self>> {
+        let path = Self::path(api_version)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents =
+            fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+        let cache: FieldCache = serde_json::from_str(&contents)
+            .with_context(|| format!("decode field cache {}", path.display()))?;
+        if cache.version != CACHE_VERSION || cache.api_version != api_version {
+            log::debug!("ignoring stale field cache {}", path.display());
+            return Ok(None);
+        }
+        Ok(Some(cache))
+    }
+
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = Self::path(&self.api_version)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?).with_context(|| format!("write {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("write {}", path.display()))?;
+        Ok(path)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FieldMeta> {
+        self.fields.get(name)
+    }
+
+    pub fn children(&self, resource: &str) -> Vec<&FieldMeta> {
+        let prefix = format!("{resource}.");
+        self.fields
+            .range(prefix.clone()..)
+            .take_while(|(name, _)| name.starts_with(&prefix))
+            .map(|(_, meta)| meta)
+            .collect()
+    }
+}
+
+pub async fn sync(client: &AdsClient, pool: &DescriptorPool, api_version: &str) -> Result<FieldCache> {
+    let method = find_method(pool, SERVICE, SEARCH_FIELDS)?;
+    let mut fields = BTreeMap::new();
+    let mut page_token: Option<String> = None;
+
+    loop {
+        let mut request = Map::new();
+        request.insert("query".to_string(), Value::String(FIELDS_QUERY.to_string()));
+        if let Some(token) = page_token.as_ref() {
+            request.insert("pageToken".to_string(), Value::String(token.clone()));
+        }
+        let message = dynamic_from_value(method.input(), Value::Object(request))?;
+        let response = dynamic_to_value(&client.unary(&method, message).await?)?;
+
+        if let Some(results) = response.get("results").and_then(|v| v.as_array()) {
+            for result in results {
+                let meta: FieldMeta =
+                    serde_json::from_value(result.clone()).context("decode GoogleAdsField")?;
+                fields.insert(meta.name.clone(), meta);
+            }
+        }
+
+        match response
+            .get("nextPageToken")
+            .and_then(|v| v.as_str())
+            .filter(|token| !token.is_empty())
+        {
+            Some(token) => page_token = Some(token.to_string()),
+            None => break,
+        }
+    }
+
+    if fields.is_empty() {
+        return Err(anyhow!("SearchGoogleAdsFields returned no fields"));
+    }
+    let synced_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    Ok(FieldCache {
+        version: CACHE_VERSION,
+        api_version: api_version.to_string(),
+        synced_at,
+        fields,
+    })
+}
+
+pub fn check_compatibility(cache: &FieldCache, text: &str, query: &Query) -> Vec<Diagnostic> {
+    let mut out = Vec::new();
+    let from = cache.get(&query.from.name);
+    let warn = |offset: usize, message: String| diagnostic(text, offset, Severity::Warning, message);
+
+    for field in &query.select {
+        let Some(meta) = cache.get(&field.name) else {
+            continue;
+        };
+        if !meta.selectable {
+            out.push(warn(field.offset, format!("{} is not selectable", field.name)));
+            continue;
+        }
+        let Some(from) = from else {
+            continue;
+        };
+        if !selectable_with(from, &query.from.name, meta) {
+            out.push(warn(
+                field.offset,
+                format!("{} cannot be selected with FROM {}", field.name, query.from.name),
+            ));
+        }
+    }
+
+    for condition in &query.conditions {
+        if let Some(meta) = cache.get(&condition.field.name)
+            && !meta.filterable
+        {
+            out.push(warn(
+                condition.field.offset,
+                format!("{} is not filterable", condition.field.name),
+            ));
+        }
+    }
+
+    for ordering in &query.order_by {
+        if let Some(meta) = cache.get(&ordering.field.name)
+            && !meta.sortable
+        {
+            out.push(warn(
+                ordering.field.offset,
+                format!("{} is not sortable", ordering.field.name),
+            ));
+        }
+    }
+
+    out
+}
+
+fn selectable_with(from: &FieldMeta, from_name: &str, field: &FieldMeta) -> bool {
+    let resource = field.name.split('.').next().unwrap_or_default();
+    if resource == from_name {
+        return true;
+    }
+    match field.category.as_str() {
+        "METRIC" | "SEGMENT" => from.selectable_with.iter().any(|f| f == &field.name),
+        _ => from
+            .selectable_with
+            .iter()
+            .chain(from.attribute_resources.iter())
+            .any(|r| r == resource),
+    }
+}
@@ -1,116 +1,462 @@
-use anyhow::Result;
-use prost_reflect::DescriptorPool;
+use anyhow::{Result, anyhow};
+use futures::StreamExt;
+use prost_reflect::{DescriptorPool, FieldDescriptor, Kind, MessageDescriptor};
+use serde::Serialize;
 use serde_json::{Map, Value, json};
+use std::cell::RefCell;
 
+use crate::auth::normalize_customer_id;
 use crate::client::AdsClient;
-use crate::command_tree::find_method;
+use crate::command_tree::{detect_api_version, find_method};
+use crate::errors::status_report;
+use crate::fields::{FieldCache, check_compatibility};
+use crate::gaql_parser::{self, Diagnostic, Query, Severity};
+use crate::output::{Interrupted, RowSink, TableColumn};
 use crate::proto_json::{dynamic_from_value, dynamic_to_value};
 
+#[derive(Clone)]
 pub struct SearchArgs {
     pub customer_id: String,
     pub query: String,
     pub use_search: bool,
+    pub all_pages: bool,
     pub page_size: Option<i64>,
     pub page_token: Option<String>,
     pub validate_only: bool,
     pub summary_row_setting: Option<String>,
     pub return_total_results_count: bool,
     pub raw: bool,
-    pub jsonl: bool,
-}
-
-pub enum Output {
-    Json(Value),
-    JsonLines(Vec<Value>),
 }
 
 const SERVICE: &str = "google-ads-service";
 const SEARCH: &str = "search";
 const SEARCH_STREAM: &str = "search-stream";
+const SUMMARY_ROW_ONLY: &str = "SUMMARY_ROW_ONLY";
+const NO_SUMMARY_ROW: &str = "NO_SUMMARY_ROW";
+const CLIENTS_QUERY: &str = "SELECT customer_client.id FROM customer_client \
+     WHERE customer_client.manager = false AND customer_client.status = 'ENABLED'";
+pub const CUSTOMER_ID_COLUMN: &str = "customer_id";
+const CUSTOMER_ID_KEY: &str = "customerId";
+
+#[derive(Debug, Serialize)]
+pub struct AccountFailure {
+    pub customer_id: String,
+    pub code: Option<String>,
+    pub message: String,
+    pub request_id: Option<String>,
+    pub error_codes: Vec<String>,
+}
+
+pub async fn search_page(client: &AdsClient, pool: &DescriptorPool, args: &SearchArgs) -> Result<Value> {
+    let method = find_method(pool, SERVICE, SEARCH)?;
+    let request = build_search_request(args)?;
+    let message = dynamic_from_value(method.input(), request)?;
+    let response = client.unary(&method, message).await?;
+    dynamic_to_value(&response)
+}
+
+pub async fn run_search(
+    client: &AdsClient,
+    pool: &DescriptorPool,
+    args: SearchArgs,
+    sink: &mut dyn RowSink,
+) -> Result<Option<Value>> {
+    let mut trailer = Trailer::default();
+    let interrupt = tokio::signal::ctrl_c();
+    tokio::pin!(interrupt);
 
-pub async fn run_search(client: &AdsClient, pool: &DescriptorPool, args: SearchArgs) -> Result<Output> {
     if args.use_search {
-        let method = find_method(pool, SERVICE, SEARCH)?;
-        let request = build_search_request(&args);
-        let message = dynamic_from_value(method.input(), request)?;
-        let response = client.unary(&method, message).await?;
-        let json = dynamic_to_value(&response)?;
-        if args.raw {
-            return Ok(Output::Json(json));
+        let mut args = args;
+        loop {
+            let json = tokio::select! {
+                json = search_page(client, pool, &args) => json?,
+                _ = &mut interrupt => return interrupted(sink),
+            };
+            write_response(&json, args.raw, sink)?;
+            trailer.update(&json);
+            sink.flush()?;
+
+            match json
+                .get("nextPageToken")
+                .and_then(|v| v.as_str())
+                .filter(|token| !token.is_empty() && args.all_pages)
+            {
+                Some(token) => args.page_token = Some(token.to_string()),
+                None => break,
+            }
         }
-        let results = json
-            .get("results")
-            .cloned()
-            .unwrap_or_else(|| Value::Array(Vec::new()));
-        return Ok(Output::Json(results));
+        sink.finish()?;
+        return Ok(trailer.into_value());
     }
 
     let method = find_method(pool, SERVICE, SEARCH_STREAM)?;
-    let request = build_search_request(&args);
+    let request = build_search_request(&args)?;
     let message = dynamic_from_value(method.input(), request)?;
 
-    if args.jsonl {
-        let mut stream = client.server_streaming_raw(&method, message).await?;
-        let mut rows = Vec::new();
-        while let Some(msg) = stream.message().await? {
-            let json = dynamic_to_value(&msg)?;
-            if args.raw {
-                rows.push(json);
-                continue;
-            }
-            if let Some(results) = json.get("results").and_then(|v| v.as_array()) {
-                for row in results {
-                    rows.push(row.clone());
-                }
-            }
-        }
-        return Ok(Output::JsonLines(rows));
+    let mut stream = client.server_stream(&method, message).await?;
+    loop {
+        let msg = tokio::select! {
+            msg = stream.message() => msg?,
+            _ = &mut interrupt => return interrupted(sink),
+        };
+        let Some(msg) = msg else {
+            break;
+        };
+        let json = dynamic_to_value(&msg)?;
+        write_response(&json, args.raw, sink)?;
+        trailer.update(&json);
+        sink.flush()?;
     }
+    sink.finish()?;
+    Ok(trailer.into_value())
+}
+
+pub async fn list_client_ids(
+    client: &AdsClient,
+    pool: &DescriptorPool,
+    root_customer_id: &str,
+    query: Option<&str>,
+) -> Result<Vec<String>> {
+    let method = find_method(pool, SERVICE, SEARCH_STREAM)?;
+    let request = json!({
+        "customerId": root_customer_id,
+        "query": query.unwrap_or(CLIENTS_QUERY),
+    });
+    let message = dynamic_from_value(method.input(), request)?;
+    let mut stream = client.server_stream(&method, message).await?;
 
-    let responses = client.server_stream(&method, message).await?;
-    let mut chunks = Vec::new();
-    let mut rows = Vec::new();
-    for msg in responses {
+    let mut ids = Vec::new();
+    while let Some(msg) = stream.message().await? {
         let json = dynamic_to_value(&msg)?;
-        if args.raw {
-            chunks.push(json);
+        let Some(results) = json.get("results").and_then(|v| v.as_array()) else {
             continue;
+        };
+        for row in results {
+            if let Some(id) = row_customer_id(row)
+                && !ids.contains(&id)
+            {
+                ids.push(id);
+            }
         }
-        if let Some(results) = json.get("results").and_then(|v| v.as_array()) {
-            for row in results {
-                rows.push(row.clone());
+    }
+    Ok(ids)
+}
+
+pub async fn run_fan_out(
+    client: &AdsClient,
+    pool: &DescriptorPool,
+    args: SearchArgs,
+    customer_ids: Vec<String>,
+    concurrency: usize,
+    sink: &mut dyn RowSink,
+) -> Result<Vec<AccountFailure>> {
+    let shared = RefCell::new(sink);
+    let mut runs = futures::stream::iter(customer_ids)
+        .map(|customer_id| {
+            let args = SearchArgs {
+                customer_id: customer_id.clone(),
+                ..args.clone()
+            };
+            let shared = &shared;
+            async move {
+                let mut tagged = TaggedSink {
+                    inner: shared,
+                    customer_id: customer_id.clone(),
+                };
+                let result = run_search(client, pool, args, &mut tagged).await;
+                (customer_id, result)
             }
+        })
+        .buffer_unordered(concurrency.max(1));
+
+    let mut failures = Vec::new();
+    while let Some((customer_id, result)) = runs.next().await {
+        let Err(err) = result else {
+            continue;
+        };
+        if err.downcast_ref::<Interrupted>().is_some() {
+            drop(runs);
+            shared.borrow_mut().finish()?;
+            return Err(err);
+        }
+        log::warn!("customer {customer_id} failed: {err:#}");
+        failures.push(account_failure(pool, customer_id, &err));
+    }
+    drop(runs);
+    shared.borrow_mut().finish()?;
+    Ok(failures)
+}
+
+fn account_failure(pool: &DescriptorPool, customer_id: String, err: &anyhow::Error) -> AccountFailure {
+    let Some(status) = err.chain().find_map(|e| e.downcast_ref::<tonic::Status>()) else {
+        return AccountFailure {
+            customer_id,
+            code: None,
+            message: format!("{err:#}"),
+            request_id: None,
+            error_codes: Vec::new(),
+        };
+    };
+    let report = status_report(pool, status);
+    AccountFailure {
+        customer_id,
+        error_codes: report
+            .errors
+            .iter()
+            .filter_map(|e| e.error_code.clone())
+            .collect(),
+        code: Some(report.code),
+        message: report.message,
+        request_id: report.request_id,
+    }
+}
+
+fn row_customer_id(row: &Value) -> Option<String> {
+    let direct = row
+        .pointer("/customerClient/id")
+        .or_else(|| row.pointer("/customer/id"))
+        .and_then(|v| v.as_str());
+    let resource = || {
+        row.pointer("/customerClient/clientCustomer")
+            .and_then(|v| v.as_str())
+    };
+    direct
+        .or_else(resource)
+        .map(normalize_customer_id)
+        .filter(|id| !id.is_empty())
+}
+
+struct TaggedSink<'a, 'b> {
+    inner: &'a RefCell<&'b mut dyn RowSink>,
+    customer_id: String,
+}
+
+impl RowSink for TaggedSink<'_, '_> {
+    fn write_row(&mut self, row: &Value) -> Result<()> {
+        let mut row = row.clone();
+        if let Some(map) = row.as_object_mut() {
+            map.insert(CUSTOMER_ID_KEY.to_string(), Value::String(self.customer_id.clone()));
         }
+        self.inner.borrow_mut().write_row(&row)
     }
 
-    if args.raw {
-        return Ok(Output::Json(Value::Array(chunks)));
+    fn flush(&mut self) -> Result<()> {
+        self.inner.borrow_mut().flush()
     }
-    Ok(Output::Json(Value::Array(rows)))
+
+    fn finish(&mut self) -> Result<()> {
+        self.inner.borrow_mut().flush()
+    }
+}
+
+fn write_response(json: &Value, raw: bool, sink: &mut dyn RowSink) -> Result<()> {
+    if raw {
+        return sink.write_row(json);
+    }
+    if let Some(results) = json.get("results").and_then(|v| v.as_array()) {
+        for row in results {
+            sink.write_row(row)?;
+        }
+    }
+    Ok(())
+}
+
+fn interrupted(sink: &mut dyn RowSink) -> Result<Option<Value>> {
+    sink.finish()?;
+    Err(Interrupted.into())
+}
+
+#[derive(Default)]
+struct Trailer {
+    total_results_count: Option<Value>,
+    summary_row: Option<Value>,
 }
 
-fn build_search_request(args: &SearchArgs) -> Value {
+impl Trailer {
+    fn update(&mut self, json: &Value) {
+        if let Some(count) = json.get("totalResultsCount") {
+            self.total_results_count = Some(count.clone());
+        }
+        if let Some(row) = json.get("summaryRow") {
+            self.summary_row = Some(row.clone());
+        }
+    }
+
+    fn into_value(self) -> Option<Value> {
+        if self.total_results_count.is_none() && self.summary_row.is_none() {
+            return None;
+        }
+        let mut map = Map::new();
+        if let Some(count) = self.total_results_count {
+            map.insert("totalResultsCount".to_string(), count);
+        }
+        if let Some(row) = self.summary_row {
+            map.insert("summaryRow".to_string(), row);
+        }
+        Some(Value::Object(map))
+    }
+}
+
+fn build_search_request(args: &SearchArgs) -> Result<Value> {
     let mut map = Map::new();
     map.insert("customerId".to_string(), Value::String(args.customer_id.clone()));
     map.insert("query".to_string(), Value::String(args.query.clone()));
 
-    if args.use_search {
-        if let Some(page_size) = args.page_size {
-            map.insert("pageSize".to_string(), json!(page_size));
+    if !args.use_search {
+        if args.validate_only {
+            return Err(anyhow!("--validate-only requires --use-search"));
         }
-        if let Some(page_token) = args.page_token.as_ref() {
-            map.insert("pageToken".to_string(), Value::String(page_token.clone()));
+        if let Some(setting) = args.summary_row_setting.as_ref() {
+            map.insert("summaryRowSetting".to_string(), Value::String(setting.clone()));
         }
+        return Ok(Value::Object(map));
+    }
+
+    if let Some(page_size) = args.page_size {
+        map.insert("pageSize".to_string(), json!(page_size));
+    }
+    if let Some(page_token) = args.page_token.as_ref() {
+        map.insert("pageToken".to_string(), Value::String(page_token.clone()));
     }
     if args.validate_only {
         map.insert("validateOnly".to_string(), Value::Bool(true));
     }
+
+    let mut settings = Map::new();
     if let Some(setting) = args.summary_row_setting.as_ref() {
-        map.insert("summaryRowSetting".to_string(), Value::String(setting.clone()));
+        if setting != NO_SUMMARY_ROW {
+            settings.insert("returnSummaryRow".to_string(), Value::Bool(true));
+        }
+        if setting == SUMMARY_ROW_ONLY {
+            settings.insert("omitResults".to_string(), Value::Bool(true));
+        }
+    }
+    if args.return_total_results_count {
+        settings.insert("returnTotalResultsCount".to_string(), Value::Bool(true));
+    }
+    if !settings.is_empty() {
+        map.insert("searchSettings".to_string(), Value::Object(settings));
+    }
+
+    Ok(Value::Object(map))
+}
+
+pub fn row_descriptor(pool: &DescriptorPool) -> Result<MessageDescriptor> {
+    let method = find_method(pool, SERVICE, SEARCH)?;
+    let results = method
+        .output()
+        .get_field_by_name("results")
+        .ok_or_else(|| anyhow!("search response has no results field"))?;
+    match results.kind() {
+        Kind::Message(row) => Ok(row),
+        _ => Err(anyhow!("search results are not messages")),
     }
-    if args.use_search && args.return_total_results_count {
-        map.insert("returnTotalResultsCount".to_string(), Value::Bool(true));
+}
+
+pub fn resolve_field(row: &MessageDescriptor, path: &str) -> Option<Vec<FieldDescriptor>> {
+    let mut message = row.clone();
+    let mut chain = Vec::new();
+    let mut parts = path.split('.').peekable();
+    while let Some(part) = parts.next() {
+        let field = message.get_field_by_name(part)?;
+        if parts.peek().is_some() {
+            let Kind::Message(next) = field.kind() else {
+                return None;
+            };
+            message = next;
+        }
+        chain.push(field);
+    }
+    Some(chain)
+}
+
+pub fn select_fields(query: &str) -> Result<Vec<String>> {
+    let parsed = gaql_parser::parse(query).map_err(|diag| anyhow!("{diag}"))?;
+    Ok(parsed.select.into_iter().map(|field| field.name).collect())
+}
+
+pub fn lint(pool: &DescriptorPool, query: &str) -> Result<(Option<Query>, Vec<Diagnostic>)> {
+    let row = row_descriptor(pool)?;
+    let (parsed, mut diagnostics) = gaql_parser::check(&row, query);
+    if let Some(parsed) = parsed.as_ref() {
+        let api_version = detect_api_version(pool).unwrap_or_default();
+        match FieldCache::load(&api_version) {
+            Ok(Some(cache)) => diagnostics.extend(check_compatibility(&cache, query, parsed)),
+            Ok(None) => log::debug!("no field cache; run `search-ads fields sync` for compatibility checks"),
+            Err(err) => log::warn!("field cache unavailable: {err:#}"),
+        }
     }
+    Ok((parsed, diagnostics))
+}
 
-    Value::Object(map)
+pub fn check_query(pool: &DescriptorPool, query: &str) -> Result<()> {
+    let (_, diagnostics) = lint(pool, query)?;
+    for diag in diagnostics.iter().filter(|d| d.severity == Severity::Warning) {
+        log::warn!("{diag}");
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>();
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!("invalid GAQL query:\n{}", errors.join("\n")))
+}
+
+pub fn table_columns(pool: &DescriptorPool, query: &str, customer_column: bool) -> Result<Vec<TableColumn>> {
+    let row = row_descriptor(pool)?;
+    let mut columns = Vec::new();
+    if customer_column {
+        columns.push(TableColumn {
+            name: CUSTOMER_ID_COLUMN.to_string(),
+            path: vec![CUSTOMER_ID_KEY.to_string()],
+            default: String::new(),
+        });
+    }
+    let selected = select_fields(query)?
+        .into_iter()
+        .map(|name| match resolve_field(&row, &name) {
+            Some(chain) => TableColumn {
+                path: chain.iter().map(|f| f.json_name().to_string()).collect(),
+                default: chain.last().map(default_cell).unwrap_or_default(),
+                name,
+            },
+            None => TableColumn {
+                path: name.split('.').map(to_camel).collect(),
+                default: String::new(),
+                name,
+            },
+        });
+    columns.extend(selected);
+    Ok(columns)
+}
+
+fn default_cell(field: &FieldDescriptor) -> String {
+    if field.is_list() || field.supports_presence() {
+        return String::new();
+    }
+    match field.kind() {
+        Kind::Enum(e) => e.default_value().name().to_string(),
+        Kind::Bool => "false".to_string(),
+        Kind::String | Kind::Bytes | Kind::Message(_) => String::new(),
+        _ => "0".to_string(),
+    }
+}
+
+fn to_camel(value: &str) -> String {
+    let mut out = String::new();
+    let mut upper = false;
+    for ch in value.chars() {
+        if ch == '_' {
+            upper = true;
+        } else if upper {
+            out.push(ch.to_ascii_uppercase());
+            upper = false;
+        } else {
+            out.push(ch);
+        }
+    }
+    out
 }
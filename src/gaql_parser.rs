@@ -0,0 +1,676 @@
+use prost_reflect::{Kind, MessageDescriptor};
+use serde::Serialize;
+use std::fmt;
+
+const DATE_RANGES: &[&str] = &[
+    "LAST_14_DAYS",
+    "LAST_30_DAYS",
+    "LAST_7_DAYS",
+    "LAST_BUSINESS_WEEK",
+    "LAST_MONTH",
+    "LAST_WEEK_MON_SUN",
+    "LAST_WEEK_SUN_SAT",
+    "THIS_MONTH",
+    "THIS_WEEK_MON_TODAY",
+    "THIS_WEEK_SUN_TODAY",
+    "TODAY",
+    "YESTERDAY",
+];
+const PARAMETERS: &[&str] = &["include_drafts", "omit_unselected_resource_names"];
+const NON_RESOURCES: &[&str] = &["metrics", "segments"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+    Word,
+    Str,
+    Number,
+    Comma,
+    LParen,
+    RParen,
+    Op,
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    offset: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldRef {
+    pub name: String,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Condition {
+    pub field: FieldRef,
+    pub operator: String,
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ordering {
+    pub field: FieldRef,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Query {
+    pub select: Vec<FieldRef>,
+    pub from: FieldRef,
+    pub conditions: Vec<Condition>,
+    pub order_by: Vec<Ordering>,
+    pub limit: Option<u64>,
+    pub parameters: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{severity}: {}:{}: {}", self.line, self.column, self.message)?;
+        if let Some(suggestion) = self.suggestion.as_ref() {
+            write!(f, " (did you mean {suggestion}?)")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse(query: &str) -> Result<Query, Diagnostic> {
+    let tokens = lex(query).map_err(|(offset, msg)| diagnostic(query, offset, Severity::Error, msg))?;
+    Parser { tokens, pos: 0 }
+        .query()
+        .map_err(|(offset, msg)| diagnostic(query, offset, Severity::Error, msg))
+}
+
+pub fn check(row: &MessageDescriptor, text: &str) -> (Option<Query>, Vec<Diagnostic>) {
+    match parse(text) {
+        Ok(query) => {
+            let diagnostics = validate(row, text, &query);
+            (Some(query), diagnostics)
+        }
+        Err(diag) => (None, vec![diag]),
+    }
+}
+
+pub fn validate(row: &MessageDescriptor, text: &str, query: &Query) -> Vec<Diagnostic> {
+    let mut out = Vec::new();
+
+    let resources = row
+        .fields()
+        .filter(|f| matches!(f.kind(), Kind::Message(_)) && !NON_RESOURCES.contains(&f.name()))
+        .map(|f| f.name().to_string())
+        .collect::<Vec<_>>();
+    if !resources.contains(&query.from.name) {
+        out.push(Diagnostic {
+            suggestion: closest(&query.from.name, resources.iter().map(String::as_str)),
+            ..diagnostic(
+                text,
+                query.from.offset,
+                Severity::Error,
+                format!("unknown resource {} in FROM", query.from.name),
+            )
+        });
+    }
+
+    let fields = query
+        .select
+        .iter()
+        .chain(query.conditions.iter().map(|c| &c.field))
+        .chain(query.order_by.iter().map(|o| &o.field));
+    for field in fields {
+        if let Some(diag) = check_field(row, text, field) {
+            out.push(diag);
+        }
+    }
+
+    for condition in &query.conditions {
+        if condition.operator != "DURING" {
+            continue;
+        }
+        for value in &condition.values {
+            if !DATE_RANGES.contains(&value.as_str()) {
+                out.push(Diagnostic {
+                    suggestion: closest(value, DATE_RANGES.iter().copied()),
+                    ..diagnostic(
+                        text,
+                        condition.field.offset,
+                        Severity::Error,
+                        format!("unknown date range {value} for DURING"),
+                    )
+                });
+            }
+        }
+    }
+
+    for (name, _) in &query.parameters {
+        if !PARAMETERS.contains(&name.as_str()) {
+            out.push(Diagnostic {
+                suggestion: closest(name, PARAMETERS.iter().copied()),
+                ..diagnostic(
+                    text,
+                    query.from.offset,
+                    Severity::Error,
+                    format!("unknown parameter {name}"),
+                )
+            });
+        }
+    }
+
+    out
+}
+
+fn check_field(row: &MessageDescriptor, text: &str, field: &FieldRef) -> Option<Diagnostic> {
+    let parts = field.name.split('.').collect::<Vec<_>>();
+    if parts.len() < 2 {
+        return Some(diagnostic(
+            text,
+            field.offset,
+            Severity::Error,
+            format!("{} is not a field; use resource.field", field.name),
+        ));
+    }
+
+    let mut message = row.clone();
+    for (idx, part) in parts.iter().enumerate() {
+        let Some(found) = message.get_field_by_name(part) else {
+            let names = message.fields().map(|f| f.name().to_string()).collect::<Vec<_>>();
+            let suggestion = closest(part, names.iter().map(String::as_str)).map(|best| {
+                let mut path = parts[..idx].to_vec();
+                path.push(&best);
+                path.join(".")
+            });
+            return Some(Diagnostic {
+                suggestion,
+                ..diagnostic(
+                    text,
+                    field.offset,
+                    Severity::Error,
+                    format!("unknown field {}", field.name),
+                )
+            });
+        };
+        if idx + 1 == parts.len() {
+            break;
+        }
+        match found.kind() {
+            Kind::Message(next) => message = next,
+            _ => {
+                return Some(diagnostic(
+                    text,
+                    field.offset,
+                    Severity::Error,
+                    format!("{} has no subfields", parts[..=idx].join(".")),
+                ));
+            }
+        }
+    }
+    None
+}
+
+fn closest<'a>(input: &str, candidates: impl Iterator<Item = &'a str>) -> Option<String> {
+    let limit = (input.len() / 3).max(2);
+    candidates
+        .map(|c| (strsim::damerau_levenshtein(input, c), c))
+        .filter(|(dist, _)| *dist <= limit)
+        .min_by_key(|(dist, _)| *dist)
+        .map(|(_, c)| c.to_string())
+}
+
+pub fn diagnostic(text: &str, offset: usize, severity: Severity, message: String) -> Diagnostic {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+    Diagnostic {
+        severity,
+        line,
+        column,
+        message,
+        suggestion: None,
+    }
+}
+
+fn lex(text: &str) -> Result<Vec<Token>, (usize, String)> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let ch = bytes[i] as char;
+        if ch.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let kind = match ch {
+            ',' => {
+                i += 1;
+                TokenKind::Comma
+            }
+            '(' => {
+                i += 1;
+                TokenKind::LParen
+            }
+            ')' => {
+                i += 1;
+                TokenKind::RParen
+            }
+            '=' => {
+                i += 1;
+                TokenKind::Op
+            }
+            '!' | '<' | '>' => {
+                i += 1;
+                if bytes.get(i) == Some(&b'=') {
+                    i += 1;
+                } else if ch == '!' {
+                    return Err((start, "expected != operator".to_string()));
+                }
+                TokenKind::Op
+            }
+            '\'' | '"' => {
+                i += 1;
+                let mut value = String::new();
+                loop {
+                    match bytes.get(i) {
+                        None => return Err((start, "unterminated string literal".to_string())),
+                        Some(b'\\') if i + 1 < bytes.len() => {
+                            let c = text[i + 1..].chars().next().unwrap_or_default();
+                            value.push(c);
+                            i += 1 + c.len_utf8();
+                        }
+                        Some(&b) if b as char == ch => {
+                            i += 1;
+                            break;
+                        }
+                        Some(_) => {
+                            let c = text[i..].chars().next().unwrap_or_default();
+                            value.push(c);
+                            i += c.len_utf8();
+                        }
+                    }
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Str,
+                    text: value,
+                    offset: start,
+                });
+                continue;
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '.' => {
+                i += 1;
+                while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                    i += 1;
+                }
+                TokenKind::Number
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'.')
+                {
+                    i += 1;
+                }
+                TokenKind::Word
+            }
+            other => return Err((start, format!("unexpected character {other:?}"))),
+        };
+        tokens.push(Token {
+            kind,
+            text: text[start..i].to_string(),
+            offset: start,
+        });
+    }
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        text: String::new(),
+        offset: text.len(),
+    });
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+type ParseResult<T> = Result<T, (usize, String)>;
+
+impl Parser {
+    fn query(&mut self) -> ParseResult<Query> {
+        self.expect_keyword("SELECT")?;
+        let mut select = vec![self.field()?];
+        while self.eat(TokenKind::Comma) {
+            select.push(self.field()?);
+        }
+
+        self.expect_keyword("FROM")?;
+        let from = self.field()?;
+
+        let mut conditions = Vec::new();
+        if self.eat_keyword("WHERE") {
+            conditions.push(self.condition()?);
+            while self.eat_keyword("AND") {
+                conditions.push(self.condition()?);
+            }
+        }
+
+        let mut order_by = Vec::new();
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let field = self.field()?;
+                let descending = if self.eat_keyword("DESC") {
+                    true
+                } else {
+                    self.eat_keyword("ASC");
+                    false
+                };
+                order_by.push(Ordering { field, descending });
+                if !self.eat(TokenKind::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let mut limit = None;
+        if self.eat_keyword("LIMIT") {
+            let token = self.next();
+            let value = (token.kind == TokenKind::Number)
+                .then(|| token.text.parse::<u64>().ok())
+                .flatten()
+                .filter(|v| *v > 0)
+                .ok_or_else(|| (token.offset, "LIMIT expects a positive integer".to_string()))?;
+            limit = Some(value);
+        }
+
+        let mut parameters = Vec::new();
+        if self.eat_keyword("PARAMETERS") {
+            loop {
+                let name = self.field()?;
+                let token = self.next();
+                if token.kind != TokenKind::Op || token.text != "=" {
+                    return Err((token.offset, "expected = after parameter name".to_string()));
+                }
+                let value = self.literal()?;
+                parameters.push((name.name, value));
+                if !self.eat(TokenKind::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let token = self.peek();
+        if token.kind != TokenKind::Eof {
+            return Err((token.offset, format!("unexpected {}", describe(token))));
+        }
+
+        Ok(Query {
+            select,
+            from,
+            conditions,
+            order_by,
+            limit,
+            parameters,
+        })
+    }
+
+    fn condition(&mut self) -> ParseResult<Condition> {
+        let field = self.field()?;
+        let token = self.next();
+        let upper = token.text.to_ascii_uppercase();
+        let (operator, values) = match (token.kind, upper.as_str()) {
+            (TokenKind::Op, _) => (token.text.clone(), vec![self.literal()?]),
+            (TokenKind::Word, "IN") => ("IN".to_string(), self.list()?),
+            (TokenKind::Word, "LIKE") => ("LIKE".to_string(), vec![self.string()?]),
+            (TokenKind::Word, "REGEXP_MATCH") => ("REGEXP_MATCH".to_string(), vec![self.string()?]),
+            (TokenKind::Word, "DURING") => ("DURING".to_string(), vec![self.word()?.to_ascii_uppercase()]),
+            (TokenKind::Word, "BETWEEN") => {
+                let low = self.literal()?;
+                self.expect_keyword("AND")?;
+                ("BETWEEN".to_string(), vec![low, self.literal()?])
+            }
+            (TokenKind::Word, "IS") => {
+                if self.eat_keyword("NOT") {
+                    self.expect_keyword("NULL")?;
+                    ("IS NOT NULL".to_string(), Vec::new())
+                } else {
+                    self.expect_keyword("NULL")?;
+                    ("IS NULL".to_string(), Vec::new())
+                }
+            }
+            (TokenKind::Word, "NOT") => {
+                let next = self.next();
+                match next.text.to_ascii_uppercase().as_str() {
+                    "IN" => ("NOT IN".to_string(), self.list()?),
+                    "LIKE" => ("NOT LIKE".to_string(), vec![self.string()?]),
+                    "REGEXP_MATCH" => ("NOT REGEXP_MATCH".to_string(), vec![self.string()?]),
+                    _ => {
+                        return Err((
+                            next.offset,
+                            "expected IN, LIKE or REGEXP_MATCH after NOT".to_string(),
+                        ));
+                    }
+                }
+            }
+            (TokenKind::Word, "CONTAINS") => {
+                let next = self.next();
+                let which = next.text.to_ascii_uppercase();
+                if !matches!(which.as_str(), "ANY" | "ALL" | "NONE") {
+                    return Err((next.offset, "expected ANY, ALL or NONE after CONTAINS".to_string()));
+                }
+                (format!("CONTAINS {which}"), self.list()?)
+            }
+            _ => {
+                return Err((
+                    token.offset,
+                    format!("expected operator after {}, found {}", field.name, describe(&token)),
+                ));
+            }
+        };
+        Ok(Condition {
+            field,
+            operator,
+            values,
+        })
+    }
+
+    fn list(&mut self) -> ParseResult<Vec<String>> {
+        let token = self.next();
+        if token.kind != TokenKind::LParen {
+            return Err((token.offset, "expected ( to start a list".to_string()));
+        }
+        let mut values = vec![self.literal()?];
+        while self.eat(TokenKind::Comma) {
+            values.push(self.literal()?);
+        }
+        let token = self.next();
+        if token.kind != TokenKind::RParen {
+            return Err((token.offset, "expected ) to close the list".to_string()));
+        }
+        Ok(values)
+    }
+
+    fn literal(&mut self) -> ParseResult<String> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Str | TokenKind::Number | TokenKind::Word => Ok(token.text),
+            _ => Err((token.offset, format!("expected a value, found {}", describe(&token)))),
+        }
+    }
+
+    fn string(&mut self) -> ParseResult<String> {
+        let token = self.next();
+        if token.kind != TokenKind::Str {
+            return Err((token.offset, format!("expected a string, found {}", describe(&token))));
+        }
+        Ok(token.text)
+    }
+
+    fn word(&mut self) -> ParseResult<String> {
+        let token = self.next();
+        if token.kind != TokenKind::Word {
+            return Err((token.offset, format!("expected a name, found {}", describe(&token))));
+        }
+        Ok(token.text)
+    }
+
+    fn field(&mut self) -> ParseResult<FieldRef> {
+        let token = self.next();
+        if token.kind != TokenKind::Word || is_keyword(&token.text) {
+            return Err((token.offset, format!("expected a field name, found {}", describe(&token))));
+        }
+        Ok(FieldRef {
+            name: token.text,
+            offset: token.offset,
+        })
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> ParseResult<()> {
+        if self.eat_keyword(keyword) {
+            return Ok(());
+        }
+        let token = self.peek();
+        Err((token.offset, format!("expected {keyword}, found {}", describe(token))))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let token = self.peek();
+        if token.kind == TokenKind::Word && token.text.eq_ignore_ascii_case(keyword) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat(&mut self, kind: TokenKind) -> bool {
+        if self.peek().kind == kind {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos.min(self.tokens.len() - 1)]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+}
+
+fn is_keyword(word: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "SELECT", "FROM", "WHERE", "AND", "ORDER", "BY", "ASC", "DESC", "LIMIT", "PARAMETERS",
+    ];
+    KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(word))
+}
+
+fn describe(token: &Token) -> String {
+    match token.kind {
+        TokenKind::Eof => "end of query".to_string(),
+        TokenKind::Str => format!("string '{}'", token.text),
+        _ => format!("'{}'", token.text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_tree::load_pool;
+    use crate::gaql::row_descriptor;
+
+    fn row() -> MessageDescriptor {
+        row_descriptor(&load_pool()).unwrap()
+    }
+
+    #[test]
+    fn parses_full_query() {
+        let query = parse(
+            "SELECT campaign.id, metrics.clicks FROM campaign \
+             WHERE campaign.status IN ('ENABLED', 'PAUSED') AND segments.date DURING LAST_7_DAYS \
+             ORDER BY metrics.clicks DESC LIMIT 10 PARAMETERS include_drafts = true",
+        )
+        .unwrap();
+        let select = query.select.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(select, ["campaign.id", "metrics.clicks"]);
+        assert_eq!(query.from.name, "campaign");
+        assert_eq!(query.conditions.len(), 2);
+        assert_eq!(query.conditions[0].operator, "IN");
+        assert_eq!(query.conditions[0].values, ["ENABLED", "PAUSED"]);
+        assert_eq!(query.conditions[1].operator, "DURING");
+        assert!(query.order_by[0].descending);
+        assert_eq!(query.limit, Some(10));
+        assert_eq!(query.parameters, [("include_drafts".to_string(), "true".to_string())]);
+    }
+
+    #[test]
+    fn string_escapes_keep_multibyte_characters() {
+        let query = parse("SELECT campaign.id FROM campaign WHERE campaign.name = 'a\\é\\'b'").unwrap();
+        assert_eq!(query.conditions[0].values, ["aé'b"]);
+    }
+
+    #[test]
+    fn reports_position_of_syntax_errors() {
+        let err = parse("SELECT campaign.id\nFROM campaign WHERE campaign.name = 'open").unwrap_err();
+        assert_eq!(err.severity, Severity::Error);
+        assert_eq!((err.line, err.column), (2, 37));
+        assert!(err.message.contains("unterminated"));
+    }
+
+    #[test]
+    fn suggests_close_field_names() {
+        let text = "SELECT campaign.nmae FROM campaign";
+        let (_, diagnostics) = check(&row(), text);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].suggestion.as_deref(), Some("campaign.name"));
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error: 1:8: unknown field campaign.nmae (did you mean campaign.name?)"
+        );
+    }
+
+    #[test]
+    fn suggests_resources_date_ranges_and_parameters() {
+        let text = "SELECT campaign.id FROM campain WHERE segments.date DURING LAST_7_DAY \
+                    PARAMETERS include_draft = true";
+        let (_, diagnostics) = check(&row(), text);
+        let suggestions = diagnostics
+            .iter()
+            .map(|d| d.suggestion.as_deref().unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(suggestions, ["campaign", "LAST_7_DAYS", "include_drafts"]);
+    }
+
+    #[test]
+    fn accepts_valid_query() {
+        let text = "SELECT campaign.id, campaign.name FROM campaign ORDER BY campaign.id";
+        let (query, diagnostics) = check(&row(), text);
+        assert!(query.is_some());
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    }
+}
@@ -0,0 +1,344 @@
+use prost_reflect::{DescriptorPool, EnumDescriptor, FieldDescriptor, Kind, MessageDescriptor};
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::command_tree::{Annotations, is_real_oneof};
+use crate::docs;
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+const RESOURCES_SEGMENT: &str = ".resources.";
+const INT64_PATTERN: &str = "^-?[0-9]+$";
+const UINT64_PATTERN: &str = "^[0-9]+$";
+
+pub struct SchemaGenerator {
+    annotations: Annotations,
+}
+
+impl SchemaGenerator {
+    pub fn new(pool: &DescriptorPool) -> Self {
+        Self {
+            annotations: Annotations::new(pool),
+        }
+    }
+
+    pub fn message_schema(&self, message: &MessageDescriptor) -> Value {
+        let mut builder = SchemaBuilder {
+            annotations: &self.annotations,
+            root: def_name(message, true),
+            defs: BTreeMap::new(),
+            queued: HashSet::new(),
+            pending: Vec::new(),
+        };
+        let mut schema = builder.message(message, true);
+        while let Some(next) = builder.pending.pop() {
+            let def = match &next {
+                Pending::Message(message, create) => builder.message(message, *create),
+                Pending::Enum(e) => enum_schema(e),
+            };
+            builder.defs.insert(next.full_name(), def);
+        }
+
+        let mut root = Map::new();
+        root.insert("$schema".to_string(), json!(DRAFT));
+        root.insert("$id".to_string(), json!(format!("{}.json", message.full_name())));
+        root.insert("title".to_string(), json!(message.full_name()));
+        if let Value::Object(body) = &mut schema {
+            root.append(body);
+        }
+        if !builder.defs.is_empty() {
+            root.insert("$defs".to_string(), json!(builder.defs));
+        }
+        Value::Object(root)
+    }
+}
+
+pub fn resource_messages(pool: &DescriptorPool) -> Vec<MessageDescriptor> {
+    pool.all_messages()
+        .filter(|message| message.parent_message().is_none())
+        .filter(|message| message.full_name().contains(RESOURCES_SEGMENT))
+        .filter(|message| message.full_name().starts_with("google.ads.googleads."))
+        .collect()
+}
+
+enum Pending {
+    Message(MessageDescriptor, bool),
+    Enum(EnumDescriptor),
+}
+
+impl Pending {
+    fn full_name(&self) -> String {
+        match self {
+            Pending::Message(message, create) => def_name(message, *create),
+            Pending::Enum(e) => e.full_name().to_string(),
+        }
+    }
+}
+
+// Resources are shared by create and update operations, so REQUIRED is only
+// enforced on the def reached through a `create` field.
+fn def_name(message: &MessageDescriptor, create: bool) -> String {
+    if create && is_resource(message) {
+        format!("{}:create", message.full_name())
+    } else {
+        message.full_name().to_string()
+    }
+}
+
+fn is_resource(message: &MessageDescriptor) -> bool {
+    message.full_name().contains(RESOURCES_SEGMENT)
+}
+
+struct SchemaBuilder<'a> {
+    annotations: &'a Annotations,
+    root: String,
+    defs: BTreeMap<String, Value>,
+    queued: HashSet<String>,
+    pending: Vec<Pending>,
+}
+
+impl SchemaBuilder<'_> {
+    fn message(&mut self, message: &MessageDescriptor, create: bool) -> Value {
+        let name = def_name(message, create);
+        let base = if name == self.root {
+            "#".to_string()
+        } else {
+            format!("#/$defs/{name}")
+        };
+        let enforce_required = create || !is_resource(message);
+        let mut properties = Map::new();
+        let mut aliases = Map::new();
+        let mut exclusive = Map::new();
+        let mut required = Vec::new();
+        let mut constraints = Vec::new();
+        for field in message.fields() {
+            let behaviors = self.annotations.behaviors(&field);
+            let mut schema = self.field(&field, field.name() == "create");
+            if let Value::Object(map) = &mut schema {
+                if let Some(comment) = docs::comment(field.full_name()) {
+                    map.insert("description".to_string(), json!(comment));
+                }
+                if behaviors.iter().any(|b| b == "OUTPUT_ONLY") {
+                    map.insert("readOnly".to_string(), json!(true));
+                }
+            }
+            let json_name = field.json_name();
+            if field.name() != json_name {
+                aliases.insert(
+                    field.name().to_string(),
+                    json!({ "$ref": format!("{base}/properties/{json_name}") }),
+                );
+                exclusive.insert(field.name().to_string(), json!({ "not": { "required": [json_name] } }));
+            }
+            if enforce_required && behaviors.iter().any(|b| b == "REQUIRED") {
+                if field.name() == json_name {
+                    required.push(json!(json_name));
+                } else {
+                    constraints.push(present(&field));
+                }
+            }
+            properties.insert(json_name.to_string(), schema);
+        }
+        properties.extend(aliases);
+
+        let mut schema = Map::new();
+        schema.insert("type".to_string(), json!("object"));
+        if let Some(comment) = docs::comment(message.full_name()) {
+            schema.insert("description".to_string(), json!(comment));
+        }
+        schema.insert("properties".to_string(), Value::Object(properties));
+        schema.insert("additionalProperties".to_string(), json!(false));
+        if !required.is_empty() {
+            schema.insert("required".to_string(), Value::Array(required));
+        }
+        if !exclusive.is_empty() {
+            schema.insert("dependentSchemas".to_string(), Value::Object(exclusive));
+        }
+
+        for oneof in message.oneofs().filter(is_real_oneof) {
+            let mut branches = oneof.fields().map(|field| present(&field)).collect::<Vec<_>>();
+            branches.push(json!({ "not": { "anyOf": branches.clone() } }));
+            constraints.push(json!({ "oneOf": branches }));
+        }
+        match constraints.len() {
+            0 => {}
+            1 => {
+                if let Some(Value::Object(constraint)) = constraints.pop() {
+                    schema.extend(constraint);
+                }
+            }
+            _ => {
+                schema.insert("allOf".to_string(), Value::Array(constraints));
+            }
+        }
+        Value::Object(schema)
+    }
+
+    fn field(&mut self, field: &FieldDescriptor, create: bool) -> Value {
+        if field.is_map()
+            && let Kind::Message(entry) = field.kind()
+        {
+            let value = self.kind(&entry.map_entry_value_field().kind(), false);
+            return json!({ "type": "object", "additionalProperties": value });
+        }
+        let item = self.kind(&field.kind(), create);
+        if field.is_list() {
+            return json!({ "type": "array", "items": item });
+        }
+        item
+    }
+
+    fn kind(&mut self, kind: &Kind, create: bool) -> Value {
+        match kind {
+            Kind::Bool => json!({ "type": "boolean" }),
+            Kind::String => json!({ "type": "string" }),
+            Kind::Bytes => json!({ "type": "string", "contentEncoding": "base64" }),
+            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => json!({ "type": "integer", "format": "int32" }),
+            Kind::Uint32 | Kind::Fixed32 => json!({ "type": "integer", "format": "uint32", "minimum": 0 }),
+            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+                json!({ "type": "string", "format": "int64", "pattern": INT64_PATTERN })
+            }
+            Kind::Uint64 | Kind::Fixed64 => {
+                json!({ "type": "string", "format": "uint64", "pattern": UINT64_PATTERN })
+            }
+            Kind::Float | Kind::Double => json!({ "type": "number" }),
+            Kind::Enum(e) => self.reference(Pending::Enum(e.clone())),
+            Kind::Message(message) => match well_known(message.full_name()) {
+                Some(schema) => schema,
+                None => self.reference(Pending::Message(message.clone(), create)),
+            },
+        }
+    }
+
+    fn reference(&mut self, target: Pending) -> Value {
+        let name = target.full_name();
+        if name == self.root {
+            return json!({ "$ref": "#" });
+        }
+        if self.queued.insert(name.clone()) {
+            self.pending.push(target);
+        }
+        json!({ "$ref": format!("#/$defs/{name}") })
+    }
+}
+
+fn present(field: &FieldDescriptor) -> Value {
+    if field.name() == field.json_name() {
+        return json!({ "required": [field.json_name()] });
+    }
+    json!({ "anyOf": [{ "required": [field.json_name()] }, { "required": [field.name()] }] })
+}
+
+fn enum_schema(e: &EnumDescriptor) -> Value {
+    let mut schema = Map::new();
+    schema.insert("type".to_string(), json!("string"));
+    if let Some(comment) = docs::comment(e.full_name()) {
+        schema.insert("description".to_string(), json!(comment));
+    }
+    schema.insert("enum".to_string(), e.values().map(|v| json!(v.name())).collect());
+    Value::Object(schema)
+}
+
+fn well_known(full_name: &str) -> Option<Value> {
+    let schema = match full_name {
+        "google.protobuf.Timestamp" => json!({ "type": "string", "format": "date-time" }),
+        "google.protobuf.Duration" => json!({ "type": "string", "pattern": "^-?[0-9]+(\\.[0-9]{1,9})?s$" }),
+        "google.protobuf.FieldMask" => json!({ "type": "string", "description": "Comma-separated lowerCamelCase field paths" }),
+        "google.protobuf.Struct" => json!({ "type": "object" }),
+        "google.protobuf.Value" => json!({}),
+        "google.protobuf.ListValue" => json!({ "type": "array" }),
+        "google.protobuf.Empty" => json!({ "type": "object", "additionalProperties": false }),
+        "google.protobuf.Any" => json!({
+            "type": "object",
+            "properties": { "@type": { "type": "string" } },
+            "required": ["@type"]
+        }),
+        "google.protobuf.DoubleValue" | "google.protobuf.FloatValue" => json!({ "type": "number" }),
+        "google.protobuf.Int64Value" => json!({ "type": "string", "format": "int64", "pattern": INT64_PATTERN }),
+        "google.protobuf.UInt64Value" => json!({ "type": "string", "format": "uint64", "pattern": UINT64_PATTERN }),
+        "google.protobuf.Int32Value" => json!({ "type": "integer", "format": "int32" }),
+        "google.protobuf.UInt32Value" => json!({ "type": "integer", "format": "uint32", "minimum": 0 }),
+        "google.protobuf.BoolValue" => json!({ "type": "boolean" }),
+        "google.protobuf.StringValue" => json!({ "type": "string" }),
+        "google.protobuf.BytesValue" => json!({ "type": "string", "contentEncoding": "base64" }),
+        _ => return None,
+    };
+    Some(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_tree::{find_method, load_pool};
+
+    fn validator_for(service: &str, method: &str) -> jsonschema::Validator {
+        let pool = load_pool();
+        let method = find_method(&pool, service, method).unwrap();
+        let schema = SchemaGenerator::new(&pool).message_schema(&method.input());
+        jsonschema::draft202012::new(&schema).unwrap()
+    }
+
+    #[test]
+    fn accepts_known_good_request() {
+        let validator = validator_for("campaign-service", "mutate-campaigns");
+        let request = json!({
+            "customerId": "1234567890",
+            "operations": [
+                {
+                    "create": {
+                        "name": "Test",
+                        "status": "PAUSED",
+                        "advertisingChannelType": "SEARCH",
+                        "campaignBudget": "customers/1234567890/campaignBudgets/1",
+                        "targetCpa": { "targetCpaMicros": "1000000" }
+                    }
+                },
+                { "update": { "resourceName": "customers/1/campaigns/2", "name": "x" }, "updateMask": "name" },
+                { "remove": "customers/1/campaigns/3" }
+            ],
+            "validateOnly": true
+        });
+        let errors = validator.iter_errors(&request).map(|e| e.to_string()).collect::<Vec<_>>();
+        assert!(errors.is_empty(), "{errors:?}");
+
+        let asset_groups = validator_for("asset-group-service", "mutate-asset-groups");
+        let request = json!({
+            "customerId": "1",
+            "operations": [
+                { "update": { "resourceName": "customers/1/assetGroups/2", "status": "PAUSED" }, "updateMask": "status" }
+            ]
+        });
+        let errors = asset_groups.iter_errors(&request).map(|e| e.to_string()).collect::<Vec<_>>();
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[test]
+    fn accepts_snake_case_field_names() {
+        let validator = validator_for("campaign-service", "mutate-campaigns");
+        let request = json!({
+            "customer_id": "1234567890",
+            "operations": [{ "update": { "resource_name": "customers/1/campaigns/2" }, "update_mask": "name" }],
+            "partial_failure": true
+        });
+        let errors = validator.iter_errors(&request).map(|e| e.to_string()).collect::<Vec<_>>();
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(!validator.is_valid(&json!({ "customerId": "1", "customer_id": "1", "operations": [] })));
+    }
+
+    #[test]
+    fn rejects_invalid_requests() {
+        let validator = validator_for("campaign-service", "mutate-campaigns");
+        for request in [
+            json!({ "operations": [] }),
+            json!({ "customerId": "1", "operations": [], "validate_olny": true }),
+            json!({ "customerId": "1", "operations": [{ "create": { "status": "BOGUS" } }] }),
+            json!({ "customerId": "1", "operations": [{ "create": { "id": 5 } }] }),
+            json!({ "customerId": "1", "operations": [{ "create": {}, "remove": "customers/1/campaigns/2" }] }),
+        ] {
+            assert!(!validator.is_valid(&request), "{request}");
+        }
+
+        let asset_groups = validator_for("asset-group-service", "mutate-asset-groups");
+        let request = json!({ "customerId": "1", "operations": [{ "create": { "status": "PAUSED" } }] });
+        assert!(!asset_groups.is_valid(&request), "{request}");
+    }
+}
@@ -0,0 +1,190 @@
+use anyhow::{Context, Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+use reqwest::{Client, Url};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::auth::{ADWORDS_SCOPE, AuthConfig, TOKEN_URL, exchange_token};
+use crate::credentials::StoredCredentials;
+use crate::token_cache::TokenCache;
+
+const AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const REVOKE_URL: &str = "https://oauth2.googleapis.com/revoke";
+
+pub async fn login(auth: &AuthConfig, client_id: &str, client_secret: &str) -> Result<StoredCredentials> {
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .context("bind loopback listener")?;
+    let redirect_uri = format!("http://127.0.0.1:{}", listener.local_addr()?.port());
+    let verifier = random_string(64);
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+    let state = random_string(32);
+
+    let mut url = Url::parse(AUTH_URL)?;
+    url.query_pairs_mut()
+        .append_pair("client_id", client_id)
+        .append_pair("redirect_uri", &redirect_uri)
+        .append_pair("response_type", "code")
+        .append_pair("scope", ADWORDS_SCOPE)
+        .append_pair("access_type", "offline")
+        .append_pair("prompt", "consent")
+        .append_pair("code_challenge", &challenge)
+        .append_pair("code_challenge_method", "S256")
+        .append_pair("state", &state);
+
+    eprintln!("Open this URL in a browser to authorize search-ads:\n\n  {url}\n");
+    eprintln!("Waiting for the redirect on {redirect_uri} ...");
+    let code = receive_code(&listener, &state).await?;
+
+    let token = exchange_token(
+        &auth.http,
+        auth.token_url(),
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("client_id", client_id),
+            ("client_secret", client_secret),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("code_verifier", verifier.as_str()),
+        ],
+    )
+    .await?;
+    let refresh_token = token
+        .refresh_token
+        .ok_or_else(|| anyhow!("token response did not include a refresh token"))?;
+
+    Ok(StoredCredentials {
+        client_id: client_id.to_string(),
+        client_secret: client_secret.to_string(),
+        refresh_token,
+    })
+}
+
+pub async fn logout(http: Result<Client>, token_url: &str, stored: &StoredCredentials) {
+    match revoke_url(token_url) {
+        Some(url) => {
+            if let Err(err) = revoke(http, &url, stored).await {
+                log::warn!("token revocation failed: {err:#}");
+            }
+        }
+        None => log::warn!("no revoke endpoint known for token URL {token_url}, skipping revocation"),
+    }
+    match TokenCache::new(token_url, &stored.client_id, &stored.refresh_token) {
+        Ok(cache) => cache.clear(),
+        Err(err) => log::warn!("could not clear token cache: {err:#}"),
+    }
+}
+
+fn revoke_url(token_url: &str) -> Option<String> {
+    if token_url == TOKEN_URL {
+        return Some(REVOKE_URL.to_string());
+    }
+    let base = token_url.strip_suffix("/token")?;
+    Some(format!("{base}/revoke"))
+}
+
+async fn revoke(http: Result<Client>, url: &str, stored: &StoredCredentials) -> Result<()> {
+    let resp = http?
+        .post(url)
+        .form(&[("token", stored.refresh_token.as_str())])
+        .send()
+        .await
+        .context("revoke refresh token")?;
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        log::warn!("token revocation returned http {status}: {body}");
+    }
+    Ok(())
+}
+
+async fn receive_code(listener: &TcpListener, state: &str) -> Result<String> {
+    loop {
+        let (mut stream, _) = listener.accept().await.context("accept redirect")?;
+        let params = match read_query(&mut stream).await {
+            Ok(params) => params,
+            Err(err) => {
+                log::debug!("ignoring loopback request: {err:#}");
+                continue;
+            }
+        };
+        if params.is_empty() {
+            respond(&mut stream, "404 Not Found", "Not found.").await;
+            continue;
+        }
+        if let Some(error) = params.get("error") {
+            respond(&mut stream, "400 Bad Request", "Authorization failed. You can close this window.").await;
+            return Err(anyhow!("authorization failed: {error}"));
+        }
+        if params.get("state").map(String::as_str) != Some(state) {
+            respond(&mut stream, "400 Bad Request", "State mismatch. You can close this window.").await;
+            return Err(anyhow!("authorization response state mismatch"));
+        }
+        let Some(code) = params.get("code") else {
+            respond(&mut stream, "400 Bad Request", "Missing authorization code.").await;
+            return Err(anyhow!("authorization response missing code"));
+        };
+        respond(&mut stream, "200 OK", "search-ads is authorized. You can close this window.").await;
+        return Ok(code.clone());
+    }
+}
+
+async fn read_query(stream: &mut TcpStream) -> Result<HashMap<String, String>> {
+    let mut buf = vec![0u8; 8192];
+    let mut len = 0;
+    while len < buf.len() {
+        let n = stream.read(&mut buf[len..]).await?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+        if buf[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+            break;
+        }
+    }
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let target = request
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .ok_or_else(|| anyhow!("malformed request"))?;
+    let url = Url::parse(&format!("http://127.0.0.1{target}"))?;
+    Ok(url.query_pairs().into_owned().collect())
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_revoke_url_from_token_url() {
+        assert_eq!(revoke_url(TOKEN_URL).as_deref(), Some(REVOKE_URL));
+        assert_eq!(
+            revoke_url("http://127.0.0.1:8080/token").as_deref(),
+            Some("http://127.0.0.1:8080/revoke")
+        );
+        assert_eq!(revoke_url("https://sso.example.com/oauth2/v1/issue"), None);
+    }
+}
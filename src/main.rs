@@ -1,80 +1,180 @@
+mod adc;
 mod auth;
 mod client;
+mod columnar;
+mod config;
 mod command_tree;
+mod credentials;
+mod docs;
+mod doctor;
+mod errors;
+mod fields;
 mod gaql;
+mod gaql_parser;
 mod json_input;
+mod json_schema;
+mod login;
 mod mutate;
+mod output;
+mod paths;
 mod proto_json;
+mod proxy;
+mod redact;
+mod retry;
+mod service_cli;
+mod token_cache;
+mod transport;
+mod wire_log;
 
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow};
 use clap::{Arg, ArgAction, Command, value_parser};
 use serde_json::{Value, json};
-use std::env;
 use std::io::Write;
-use std::time::Duration;
 
-use auth::{AuthConfig, normalize_customer_id};
+use auth::{TokenProvider, normalize_customer_id};
 use client::AdsClient;
-use command_tree::{CommandTree, build_tree, describe_method, load_pool, find_method};
-use gaql::{SearchArgs, Output as GaqlOutput};
+use columnar::{ColumnarFormat, ColumnarSink};
+use credentials::StoredCredentials;
+use config::{load_config, load_config_with_store};
+use command_tree::{
+    CommandTree, EnumDef, FieldDef, MessageDef, build_tree, describe_method, describe_type, find_method, load_pool,
+};
+use fields::FieldCache;
+use gaql::SearchArgs;
 use json_input::read_json_input;
+use json_schema::{SchemaGenerator, resource_messages};
 use mutate::MutateArgs;
+use output::{CsvSink, Interrupted, PartialFailure, RowSink, json_sink, stdout_json_sink};
 use proto_json::{dynamic_from_value, dynamic_to_value};
+use retry::ServicePolicies;
+use wire_log::WireLog;
 
 #[tokio::main]
 async fn main() {
-    if let Err(err) = run().await {
-        eprintln!("error: {err}");
-        for (idx, cause) in err.chain().skip(1).enumerate() {
-            eprintln!("  caused by[{idx}]: {cause}");
+    let pool = load_pool();
+    let tree = build_tree(&pool);
+    let cli = build_cli(&pool, &tree);
+    let matches = cli.get_matches();
+
+    if let Err(err) = run(&pool, &tree, &matches).await {
+        if err.downcast_ref::<Interrupted>().is_some() {
+            std::process::exit(130);
         }
-        if let Some(transport) = err.downcast_ref::<tonic::transport::Error>() {
-            eprintln!("transport debug: {transport:?}");
+        if err.downcast_ref::<PartialFailure>().is_some() {
+            std::process::exit(3);
         }
-        if let Some(status) = err.downcast_ref::<tonic::Status>() {
-            eprintln!("grpc status: {status:?}");
+        let json_errors = matches
+            .get_one::<String>("error_format")
+            .is_some_and(|format| format == "json");
+        if json_errors {
+            report_error_json(&pool, &err);
+        } else {
+            report_error(&pool, &err);
         }
         std::process::exit(1);
     }
 }
 
-async fn run() -> Result<()> {
-    let pool = load_pool();
-    let tree = build_tree(&pool);
-    let cli = build_cli(&tree);
-    let matches = cli.get_matches();
+fn report_error(pool: &prost_reflect::DescriptorPool, err: &anyhow::Error) {
+    eprintln!("error: {err}");
+    for (idx, cause) in err.chain().skip(1).enumerate() {
+        eprintln!("  caused by[{idx}]: {cause}");
+    }
+    if let Some(transport) = err.downcast_ref::<tonic::transport::Error>() {
+        eprintln!("transport debug: {transport:?}");
+    }
+    if let Some(status) = err.chain().find_map(|e| e.downcast_ref::<tonic::Status>()) {
+        for line in errors::status_report(pool, status).lines() {
+            eprintln!("{line}");
+        }
+    }
+}
+
+fn report_error_json(pool: &prost_reflect::DescriptorPool, err: &anyhow::Error) {
+    let causes = err.chain().skip(1).map(|c| c.to_string()).collect::<Vec<_>>();
+    let status = err
+        .chain()
+        .find_map(|e| e.downcast_ref::<tonic::Status>())
+        .map(|status| errors::status_report(pool, status).to_json());
+    let value = json!({
+        "error": err.to_string(),
+        "causes": causes,
+        "status": status,
+    });
+    eprintln!("{value}");
+}
 
+async fn run(
+    pool: &prost_reflect::DescriptorPool,
+    tree: &CommandTree,
+    matches: &clap::ArgMatches,
+) -> Result<()> {
+    init_logging(matches);
+    if let Some(matches) = matches.subcommand_matches("doctor") {
+        return handle_doctor(pool, matches).await;
+    }
+    if let Some(matches) = matches.subcommand_matches("auth") {
+        if let Some(matches) = matches.subcommand_matches("status") {
+            return handle_doctor(pool, matches).await;
+        }
+        return handle_auth(matches).await;
+    }
+    if let Some(config) = matches.subcommand_matches("config")
+        && let Some(matches) = config.subcommand_matches("show")
+    {
+        return handle_config_show(matches);
+    }
     if let Some(matches) = matches.subcommand_matches("list") {
-        return handle_list(&tree, matches);
+        return handle_list(tree, matches);
     }
     if let Some(matches) = matches.subcommand_matches("describe") {
-        return handle_describe(&pool, matches);
+        return handle_describe(pool, matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("describe-type") {
+        return handle_describe_type(pool, matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("schema") {
+        return handle_schema(pool, tree, matches);
     }
     if let Some(matches) = matches.subcommand_matches("tree") {
-        return handle_tree(&tree, matches);
+        return handle_tree(tree, matches);
+    }
+    if let Some(fields) = matches.subcommand_matches("fields")
+        && let Some(matches) = fields.subcommand_matches("show")
+    {
+        return handle_fields_show(tree, matches);
+    }
+    if let Some(gaql) = matches.subcommand_matches("gaql") {
+        if let Some(matches) = gaql.subcommand_matches("lint") {
+            return handle_lint(pool, matches);
+        }
+        if let Some(matches) = gaql.subcommand_matches("search")
+            && !matches.get_flag("no_validate")
+            && let Some(query) = matches.get_one::<String>("query")
+        {
+            gaql::check_query(pool, query)?;
+        }
     }
 
-    let config = load_config(&matches)?;
-    let auth = AuthConfig {
-        access_token: config.access_token.clone(),
-        client_id: config.client_id.clone(),
-        client_secret: config.client_secret.clone(),
-        refresh_token: config.refresh_token.clone(),
-    };
-    let access_token = auth::resolve_access_token(&auth).await?;
-    let timeout = matches
-        .get_one::<u64>("timeout")
-        .copied()
-        .map(Duration::from_secs);
+    let config = load_config(matches)?;
+    let auth = config.auth_config(!matches.get_flag("no_token_cache"))?;
+    let tokens = TokenProvider::new(auth).await?;
+    let developer_token = config.developer_token()?;
+    let policies = ServicePolicies::load()?
+        .with_max_attempts(matches.get_one::<u32>("max_attempts").copied());
 
+    let login_customer_id = config.login_customer_id.clone();
     let client = AdsClient::connect(
         &config.endpoint,
-        config.developer_token,
-        config.login_customer_id,
-        access_token,
-        timeout,
+        developer_token,
+        config.login_customer_id.clone(),
+        tokens,
+        config.timeout,
+        policies,
+        &config.transport,
     )
-    .await?;
+    .await?
+    .with_wire_log(wire_log(pool, matches)?);
 
     let pretty = matches.get_flag("pretty");
     let jsonl = matches.get_flag("jsonl");
@@ -84,29 +184,90 @@ async fn run() -> Result<()> {
         let matches = matches
             .subcommand_matches("search")
             .ok_or_else(|| anyhow!("gaql search required"))?;
-        let customer_id = read_customer_id(matches)?;
+        let customer_ids_from = matches.get_one::<String>("customer_ids_from").cloned();
+        let fan_out = matches.get_flag("all_clients") || customer_ids_from.is_some();
+        let customer_id = if fan_out {
+            login_customer_id
+                .clone()
+                .map(Ok)
+                .unwrap_or_else(|| read_customer_id(matches, &config))?
+        } else {
+            read_customer_id(matches, &config)?
+        };
         let query = matches
             .get_one::<String>("query")
             .ok_or_else(|| anyhow!("--query required"))?
             .to_string();
+        let all_pages = matches.get_flag("all_pages");
         let args = SearchArgs {
             customer_id,
             query,
-            use_search: matches.get_flag("use_search"),
+            use_search: matches.get_flag("use_search") || all_pages,
+            all_pages,
             page_size: matches.get_one::<i64>("page_size").copied(),
             page_token: matches.get_one::<String>("page_token").cloned(),
             validate_only: matches.get_flag("validate_only"),
             summary_row_setting: matches.get_one::<String>("summary_row_setting").cloned(),
             return_total_results_count: matches.get_flag("return_total_results_count"),
             raw,
-            jsonl,
         };
-        let output = gaql::run_search(&client, &pool, args).await?;
-        return write_gaql_output(output, pretty);
+        if raw && args.use_search && !all_pages && !fan_out {
+            let response = gaql::search_page(&client, pool, &args).await?;
+            return write_json(&response, pretty);
+        }
+        if fan_out {
+            let root = args.customer_id.clone();
+            let customer_ids =
+                gaql::list_client_ids(&client, pool, &root, customer_ids_from.as_deref()).await?;
+            eprintln!("running query across {} accounts under {root}", customer_ids.len());
+            let concurrency = matches
+                .get_one::<usize>("concurrency")
+                .copied()
+                .ok_or_else(|| anyhow!("--concurrency required"))?;
+            let mut sink = gaql_sink(pool, matches, &args.query, jsonl, pretty, raw, true)?;
+            let total = customer_ids.len();
+            let failures =
+                gaql::run_fan_out(&client, pool, args, customer_ids, concurrency, sink.as_mut()).await?;
+            let failed = failures.len();
+            if failed > 0 {
+                eprintln!("{failed} of {total} accounts failed");
+                for failure in &failures {
+                    eprintln!("  {}: {}", failure.customer_id, failure.message);
+                }
+            }
+            if let Some(path) = matches.get_one::<String>("error_report") {
+                let report = json!({
+                    "accounts": total,
+                    "succeeded": total - failed,
+                    "failed": failures,
+                });
+                std::fs::write(path, serde_json::to_vec_pretty(&report)?)
+                    .with_context(|| format!("write {path}"))?;
+            }
+            if failed > 0 && !matches.get_flag("allow_partial") {
+                return Err(PartialFailure { failed, total }.into());
+            }
+            return Ok(());
+        }
+        let mut sink = gaql_sink(pool, matches, &args.query, jsonl, pretty, raw, false)?;
+        let trailer = gaql::run_search(&client, pool, args, sink.as_mut()).await?;
+        if let Some(trailer) = trailer {
+            eprintln!("{}", serde_json::to_string(&trailer)?);
+        }
+        return Ok(());
+    }
+
+    if let Some(fields) = matches.subcommand_matches("fields")
+        && fields.subcommand_matches("sync").is_some()
+    {
+        let cache = fields::sync(&client, pool, &tree.api_version).await?;
+        let path = cache.save()?;
+        eprintln!("synced {} fields to {}", cache.fields.len(), path.display());
+        return Ok(());
     }
 
     if let Some(matches) = matches.subcommand_matches("mutate") {
-        let customer_id = read_customer_id(matches)?;
+        let customer_id = read_customer_id(matches, &config)?;
         let body = matches
             .get_one::<String>("body")
             .map(|v| read_json_input(v))
@@ -123,7 +284,7 @@ async fn run() -> Result<()> {
             validate_only: matches.get_flag("validate_only"),
             response_content_type: matches.get_one::<String>("response_content_type").cloned(),
         };
-        let output = mutate::run_mutate(&client, &pool, args).await?;
+        let output = mutate::run_mutate(&client, pool, args).await?;
         return write_json(&output, pretty);
     }
 
@@ -137,40 +298,64 @@ async fn run() -> Result<()> {
         let body = matches
             .get_one::<String>("body")
             .ok_or_else(|| anyhow!("--body required"))?;
-        let method_desc = find_method(&pool, service, method)?;
+        let method_desc = find_method(pool, service, method)?;
         if method_desc.is_client_streaming() {
             return Err(anyhow!("client streaming is not supported"));
         }
 
         let body_value = read_json_input(body)?;
         let request = dynamic_from_value(method_desc.input(), body_value)?;
+        return call_method(&client, &method_desc, request, jsonl, pretty).await;
+    }
+
+    if let Some((service, matches)) = matches.subcommand()
+        && let Some((method, matches)) = matches.subcommand()
+        && tree.services.iter().any(|s| s.name == service)
+    {
+        let method_desc = find_method(pool, service, method)?;
+        let body = service_cli::request_body(&method_desc, matches, config.customer_id.as_deref())?;
+        let request = dynamic_from_value(method_desc.input(), body)?;
+        return call_method(&client, &method_desc, request, jsonl, pretty).await;
+    }
 
-        if method_desc.is_server_streaming() {
-            if jsonl {
-                let mut stream = client.server_streaming_raw(&method_desc, request).await?;
-                while let Some(msg) = stream.message().await? {
-                    let json = dynamic_to_value(&msg)?;
-                    write_stdout_line(&serde_json::to_string(&json)?)?;
+    Err(anyhow!("command required"))
+}
+
+async fn call_method(
+    client: &AdsClient,
+    method: &prost_reflect::MethodDescriptor,
+    request: prost_reflect::DynamicMessage,
+    jsonl: bool,
+    pretty: bool,
+) -> Result<()> {
+    if method.is_server_streaming() {
+        let mut stream = client.server_stream(method, request).await?;
+        let mut sink = stdout_json_sink(jsonl, pretty);
+        let interrupt = tokio::signal::ctrl_c();
+        tokio::pin!(interrupt);
+        loop {
+            let msg = tokio::select! {
+                msg = stream.message() => msg?,
+                _ = &mut interrupt => {
+                    sink.finish()?;
+                    return Err(Interrupted.into());
                 }
-                return Ok(());
-            }
-            let responses = client.server_stream(&method_desc, request).await?;
-            let mut values = Vec::new();
-            for msg in responses {
-                values.push(dynamic_to_value(&msg)?);
-            }
-            return write_json(&Value::Array(values), pretty);
+            };
+            let Some(msg) = msg else {
+                break;
+            };
+            sink.write_row(&dynamic_to_value(&msg)?)?;
+            sink.flush()?;
         }
-
-        let response = client.unary(&method_desc, request).await?;
-        let json = dynamic_to_value(&response)?;
-        return write_json(&json, pretty);
+        return sink.finish();
     }
 
-    Err(anyhow!("command required"))
+    let response = client.unary(method, request).await?;
+    let json = dynamic_to_value(&response)?;
+    write_json(&json, pretty)
 }
 
-fn build_cli(tree: &CommandTree) -> Command {
+fn build_cli(pool: &prost_reflect::DescriptorPool, tree: &CommandTree) -> Command {
     let mut cmd = Command::new("search-ads")
         .about("Google Ads API CLI (gRPC, dynamic)")
         .subcommand_required(true)
@@ -203,6 +388,14 @@ fn build_cli(tree: &CommandTree) -> Command {
                 .value_name("TOKEN")
                 .help("Developer token (env: GOOGLE_ADS_DEVELOPER_TOKEN)"),
         )
+        .arg(
+            Arg::new("developer_token_file")
+                .long("developer-token-file")
+                .global(true)
+                .value_name("FILE")
+                .conflicts_with("developer_token")
+                .help("Read the developer token from a file (- for stdin)"),
+        )
         .arg(
             Arg::new("access_token")
                 .long("access-token")
@@ -210,6 +403,14 @@ fn build_cli(tree: &CommandTree) -> Command {
                 .value_name("TOKEN")
                 .help("Access token (env: GOOGLE_ADS_ACCESS_TOKEN)"),
         )
+        .arg(
+            Arg::new("access_token_file")
+                .long("access-token-file")
+                .global(true)
+                .value_name("FILE")
+                .conflicts_with("access_token")
+                .help("Read the access token from a file (- for stdin)"),
+        )
         .arg(
             Arg::new("client_id")
                 .long("client-id")
@@ -224,6 +425,14 @@ fn build_cli(tree: &CommandTree) -> Command {
                 .value_name("SECRET")
                 .help("OAuth client secret (env: GOOGLE_ADS_CLIENT_SECRET)"),
         )
+        .arg(
+            Arg::new("client_secret_file")
+                .long("client-secret-file")
+                .global(true)
+                .value_name("FILE")
+                .conflicts_with("client_secret")
+                .help("Read the OAuth client secret from a file (- for stdin)"),
+        )
         .arg(
             Arg::new("refresh_token")
                 .long("refresh-token")
@@ -231,6 +440,28 @@ fn build_cli(tree: &CommandTree) -> Command {
                 .value_name("TOKEN")
                 .help("OAuth refresh token (env: GOOGLE_ADS_REFRESH_TOKEN)"),
         )
+        .arg(
+            Arg::new("refresh_token_file")
+                .long("refresh-token-file")
+                .global(true)
+                .value_name("FILE")
+                .conflicts_with("refresh_token")
+                .help("Read the OAuth refresh token from a file (- for stdin)"),
+        )
+        .arg(
+            Arg::new("service_account_file")
+                .long("service-account-file")
+                .global(true)
+                .value_name("FILE")
+                .help("Service account JSON key (env: GOOGLE_ADS_JSON_KEY_FILE_PATH)"),
+        )
+        .arg(
+            Arg::new("impersonated_email")
+                .long("impersonated-email")
+                .global(true)
+                .value_name("EMAIL")
+                .help("User to impersonate via domain-wide delegation (env: GOOGLE_ADS_IMPERSONATED_EMAIL)"),
+        )
         .arg(
             Arg::new("login_customer_id")
                 .long("login-customer-id")
@@ -252,12 +483,99 @@ fn build_cli(tree: &CommandTree) -> Command {
                 .value_parser(value_parser!(u64))
                 .help("Request timeout in seconds"),
         )
+        .arg(
+            Arg::new("max_attempts")
+                .long("max-attempts")
+                .global(true)
+                .value_name("N")
+                .value_parser(value_parser!(u32).range(1..))
+                .help("Max attempts for retryable gRPC errors (1 disables retries)"),
+        )
+        .arg(
+            Arg::new("token_url")
+                .long("token-url")
+                .global(true)
+                .value_name("URL")
+                .help("OAuth token endpoint (env: GOOGLE_ADS_TOKEN_URL)"),
+        )
+        .arg(
+            Arg::new("ca_bundle")
+                .long("ca-bundle")
+                .global(true)
+                .value_name("PEM")
+                .help("Extra trusted CA certificates for gRPC and OAuth (env: GOOGLE_ADS_CA_BUNDLE)"),
+        )
+        .arg(
+            Arg::new("client_cert")
+                .long("client-cert")
+                .global(true)
+                .value_name("PEM")
+                .help("mTLS client certificate (env: GOOGLE_ADS_CLIENT_CERT)"),
+        )
+        .arg(
+            Arg::new("client_key")
+                .long("client-key")
+                .global(true)
+                .value_name("PEM")
+                .help("mTLS client private key (env: GOOGLE_ADS_CLIENT_KEY)"),
+        )
+        .arg(
+            Arg::new("authority")
+                .long("authority")
+                .global(true)
+                .value_name("HOST[:PORT]")
+                .help("Override the :authority header and TLS server name (env: GOOGLE_ADS_AUTHORITY)"),
+        )
+        .arg(
+            Arg::new("proxy")
+                .long("proxy")
+                .global(true)
+                .value_name("URL")
+                .help("http:// (CONNECT) or socks5[h]:// proxy for gRPC and OAuth (env: GOOGLE_ADS_PROXY; default: HTTPS_PROXY/ALL_PROXY, honoring NO_PROXY)"),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .global(true)
+                .value_name("NAME")
+                .help("Profile from ~/.config/search-ads/config.toml (env: SEARCH_ADS_PROFILE)"),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .global(true)
+                .value_name("FILE")
+                .help("Config file: config.toml or google-ads.yaml (env: SEARCH_ADS_CONFIG)"),
+        )
+        .arg(
+            Arg::new("no_token_cache")
+                .long("no-token-cache")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Always refresh the OAuth access token instead of reusing the cached one"),
+        )
+        .arg(
+            Arg::new("log_requests")
+                .long("log-requests")
+                .global(true)
+                .value_name("FILE")
+                .help("Append one JSON line per RPC (request, response, status, request-id, latency) to FILE"),
+        )
         .arg(
             Arg::new("debug")
                 .long("debug")
                 .global(true)
                 .action(ArgAction::SetTrue)
                 .help("Enable debug logging"),
+        )
+        .arg(
+            Arg::new("error_format")
+                .long("error-format")
+                .global(true)
+                .value_name("FORMAT")
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .help("Error output format on stderr"),
         );
 
     cmd = cmd.subcommand(
@@ -276,6 +594,7 @@ fn build_cli(tree: &CommandTree) -> Command {
             .about("Describe a service method")
             .arg(Arg::new("service").required(true))
             .arg(Arg::new("method").required(true))
+            .arg(depth_arg())
             .arg(
                 Arg::new("json")
                     .long("json")
@@ -284,6 +603,54 @@ fn build_cli(tree: &CommandTree) -> Command {
             ),
     );
 
+    cmd = cmd.subcommand(
+        Command::new("describe-type")
+            .about("Describe a message or enum by full name")
+            .arg(
+                Arg::new("type")
+                    .required(true)
+                    .value_name("FULL_NAME")
+                    .help("e.g. google.ads.googleads.v23.services.CampaignOperation"),
+            )
+            .arg(depth_arg())
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .action(ArgAction::SetTrue)
+                    .help("Emit machine-readable JSON"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("schema")
+            .about("Emit JSON Schema (draft 2020-12) for request messages")
+            .arg(
+                Arg::new("service")
+                    .required_unless_present("all")
+                    .help("Service name (e.g. campaign-service)"),
+            )
+            .arg(
+                Arg::new("method")
+                    .required_unless_present("all")
+                    .help("Method name (e.g. mutate-campaigns)"),
+            )
+            .arg(
+                Arg::new("all")
+                    .long("all")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with_all(["service", "method"])
+                    .requires("out")
+                    .help("Write schemas for every request and resource message"),
+            )
+            .arg(
+                Arg::new("out")
+                    .long("out")
+                    .value_name("DIR")
+                    .requires("all")
+                    .help("Output directory for --all (one <full.name>.json per message)"),
+            ),
+    );
+
     cmd = cmd.subcommand(
         Command::new("tree")
             .about("Show full command tree")
@@ -315,6 +682,45 @@ fn build_cli(tree: &CommandTree) -> Command {
                             .required(true)
                             .help("GAQL query"),
                     )
+                    .arg(
+                        Arg::new("all_clients")
+                            .long("all-clients")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with("customer_ids_from")
+                            .help("Run the query for every enabled client account under the login customer"),
+                    )
+                    .arg(
+                        Arg::new("customer_ids_from")
+                            .long("customer-ids-from")
+                            .value_name("GAQL")
+                            .help("Run the query for each customer id returned by this GAQL (customer_client.id)"),
+                    )
+                    .arg(
+                        Arg::new("concurrency")
+                            .long("concurrency")
+                            .value_name("N")
+                            .value_parser(value_parser!(usize))
+                            .default_value("8")
+                            .help("Accounts queried in parallel for --all-clients/--customer-ids-from"),
+                    )
+                    .arg(
+                        Arg::new("error_report")
+                            .long("error-report")
+                            .value_name("FILE")
+                            .help("Write the per-account failure report as JSON"),
+                    )
+                    .arg(
+                        Arg::new("allow_partial")
+                            .long("allow-partial")
+                            .action(ArgAction::SetTrue)
+                            .help("Exit 0 even if some accounts failed (default: exit 3)"),
+                    )
+                    .arg(
+                        Arg::new("no_validate")
+                            .long("no-validate")
+                            .action(ArgAction::SetTrue)
+                            .help("Skip local GAQL validation before sending the query"),
+                    )
                     .arg(
                         Arg::new("use_search")
                             .long("use-search")
@@ -350,6 +756,106 @@ fn build_cli(tree: &CommandTree) -> Command {
                             .long("return-total-results-count")
                             .action(ArgAction::SetTrue)
                             .help("Return total results count"),
+                    )
+                    .arg(
+                        Arg::new("all_pages")
+                            .long("all-pages")
+                            .action(ArgAction::SetTrue)
+                            .help("Follow next_page_token until exhausted (implies --use-search)"),
+                    )
+                    .arg(
+                        Arg::new("format")
+                            .long("format")
+                            .value_name("FORMAT")
+                            .value_parser(["json", "jsonl", "csv", "tsv", "parquet", "arrow"])
+                            .help("Output format (csv/tsv/parquet/arrow flatten rows into SELECT field columns)"),
+                    )
+                    .arg(
+                        Arg::new("output")
+                            .long("output")
+                            .value_name("FILE")
+                            .help("Write results to a file instead of stdout (required for parquet/arrow)"),
+                    )
+                    .arg(
+                        Arg::new("list_separator")
+                            .long("list-separator")
+                            .value_name("SEP")
+                            .default_value(";")
+                            .help("Separator for repeated values in csv/tsv cells"),
+                    ),
+            ),
+    );
+
+    cmd = cmd.mut_subcommand("gaql", |gaql| {
+        gaql.subcommand(
+            Command::new("lint")
+                .about("Validate GAQL locally against the GoogleAdsRow schema")
+                .arg(
+                    Arg::new("query")
+                        .long("query")
+                        .value_name("GAQL")
+                        .required(true)
+                        .help("GAQL query"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Emit machine-readable JSON"),
+                ),
+        )
+    });
+
+    cmd = cmd.subcommand(
+        Command::new("auth")
+            .about("Manage stored OAuth credentials")
+            .subcommand_required(true)
+            .subcommand(
+                Command::new("login")
+                    .about("Authorize in a browser and store a refresh token (needs --client-id/--client-secret of a Desktop OAuth client)")
+                    .arg(
+                        Arg::new("encrypt")
+                            .long("encrypt")
+                            .action(ArgAction::SetTrue)
+                            .help("Encrypt the stored credentials with a passphrase (env: SEARCH_ADS_PASSPHRASE)"),
+                    ),
+            )
+            .subcommand(Command::new("logout").about("Revoke and delete the stored refresh token"))
+            .subcommand(doctor_command("status")),
+    );
+
+    cmd = cmd.subcommand(doctor_command("doctor"));
+
+    cmd = cmd.subcommand(
+        Command::new("config")
+            .about("Inspect resolved configuration")
+            .subcommand_required(true)
+            .subcommand(
+                Command::new("show")
+                    .about("Show each resolved setting and where it came from")
+                    .arg(
+                        Arg::new("json")
+                            .long("json")
+                            .action(ArgAction::SetTrue)
+                            .help("Emit machine-readable JSON"),
+                    ),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("fields")
+            .about("GoogleAdsFieldService metadata cache")
+            .subcommand_required(true)
+            .subcommand(Command::new("sync").about("Download field metadata into the local cache"))
+            .subcommand(
+                Command::new("show")
+                    .about("Show cached metadata for a field or resource")
+                    .arg(Arg::new("name").required(true))
+                    .arg(
+                        Arg::new("json")
+                            .long("json")
+                            .action(ArgAction::SetTrue)
+                            .help("Emit machine-readable JSON"),
                     ),
             ),
     );
@@ -423,10 +929,13 @@ fn build_cli(tree: &CommandTree) -> Command {
             ),
     );
 
-    if tree.services.is_empty() {
-        return cmd;
+    let reserved = cmd
+        .get_arguments()
+        .map(|arg| arg.get_id().to_string())
+        .collect::<Vec<_>>();
+    for service in service_cli::service_commands(pool, tree, &reserved) {
+        cmd = cmd.subcommand(service);
     }
-
     cmd
 }
 
@@ -436,14 +945,36 @@ fn handle_list(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
     }
 
     for service in &tree.services {
-        write_stdout_line(&service.name)?;
+        write_stdout_line(&summary_line(&service.name, service.comment.as_deref(), 0))?;
+        let width = service.methods.iter().map(|m| m.name.len()).max().unwrap_or_default();
         for method in &service.methods {
-            write_stdout_line(&format!("  {}", method.name))?;
+            write_stdout_line(&summary_line(&format!("  {}", method.name), method.comment.as_deref(), width + 2))?;
         }
     }
     Ok(())
 }
 
+fn summary_line(name: &str, comment: Option<&str>, width: usize) -> String {
+    match comment.map(docs::summary).filter(|s| !s.is_empty()) {
+        Some(summary) => format!("{name:width$}  {summary}"),
+        None => name.to_string(),
+    }
+}
+
+fn write_comment_lines(comment: Option<&str>, indent: &str) -> Result<()> {
+    for line in comment.unwrap_or_default().lines() {
+        write_stdout_line(format!("{indent}{line}").trim_end())?;
+    }
+    Ok(())
+}
+
+fn write_summary_line(comment: Option<&str>, prefix: &str) -> Result<()> {
+    match comment.map(docs::summary).filter(|s| !s.is_empty()) {
+        Some(summary) => write_stdout_line(&format!("{prefix}{summary}")),
+        None => Ok(()),
+    }
+}
+
 fn handle_describe(pool: &prost_reflect::DescriptorPool, matches: &clap::ArgMatches) -> Result<()> {
     let service = matches
         .get_one::<String>("service")
@@ -452,15 +983,325 @@ fn handle_describe(pool: &prost_reflect::DescriptorPool, matches: &clap::ArgMatc
         .get_one::<String>("method")
         .ok_or_else(|| anyhow!("method required"))?;
 
-    let description = describe_method(pool, service, method)?;
+    let depth = matches.get_one::<usize>("depth").copied().unwrap_or_default();
+    let description = describe_method(pool, service, method, depth)?;
     if matches.get_flag("json") {
         return write_json(&serde_json::to_value(description)?, true);
     }
 
     write_stdout_line(&format!("{} {}", description.service, description.method))?;
+    write_comment_lines(description.comment.as_deref(), "  # ")?;
+    write_stdout_line(&format!("input: {}", description.input_type))?;
+    write_stdout_line("fields:")?;
+    write_field_lines(&description.fields, "  ")?;
+    write_stdout_line(&format!("output: {}", description.output_type))?;
     write_stdout_line("fields:")?;
-    for field in description.fields {
-        write_stdout_line(&format!("  {} ({})", field.json_name, field.kind))?;
+    write_field_lines(&description.output_fields, "  ")?;
+    write_type_lines(&description.messages, &description.enums)
+}
+
+fn handle_schema(pool: &prost_reflect::DescriptorPool, tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let generator = SchemaGenerator::new(pool);
+    if !matches.get_flag("all") {
+        let service = matches
+            .get_one::<String>("service")
+            .ok_or_else(|| anyhow!("service required"))?;
+        let method = matches
+            .get_one::<String>("method")
+            .ok_or_else(|| anyhow!("method required"))?;
+        let method_desc = find_method(pool, service, method)?;
+        return write_json(&generator.message_schema(&method_desc.input()), true);
+    }
+
+    let out = matches
+        .get_one::<String>("out")
+        .map(std::path::Path::new)
+        .ok_or_else(|| anyhow!("--out required with --all"))?;
+    std::fs::create_dir_all(out).with_context(|| format!("create {}", out.display()))?;
+    let mut messages = std::collections::BTreeMap::new();
+    for service in &tree.services {
+        for method in &service.methods {
+            let input = find_method(pool, &service.name, &method.name)?.input();
+            messages.insert(input.full_name().to_string(), input);
+        }
+    }
+    for message in resource_messages(pool) {
+        messages.insert(message.full_name().to_string(), message);
+    }
+    for (name, message) in &messages {
+        let path = out.join(format!("{name}.json"));
+        let body = serde_json::to_vec_pretty(&generator.message_schema(message))?;
+        std::fs::write(&path, body).with_context(|| format!("write {}", path.display()))?;
+    }
+    eprintln!("wrote {} schemas to {}", messages.len(), out.display());
+    Ok(())
+}
+
+fn handle_describe_type(pool: &prost_reflect::DescriptorPool, matches: &clap::ArgMatches) -> Result<()> {
+    let name = matches
+        .get_one::<String>("type")
+        .ok_or_else(|| anyhow!("type required"))?;
+    let depth = matches.get_one::<usize>("depth").copied().unwrap_or_default();
+    let description = describe_type(pool, name, depth)?;
+    if matches.get_flag("json") {
+        return write_json(&serde_json::to_value(description)?, true);
+    }
+    write_type_lines(&description.messages, &description.enums)
+}
+
+fn write_type_lines(messages: &[MessageDef], enums: &[EnumDef]) -> Result<()> {
+    for message in messages {
+        write_stdout_line(&format!("message {}", message.full_name))?;
+        write_comment_lines(message.comment.as_deref(), "  # ")?;
+        write_field_lines(&message.fields, "  ")?;
+    }
+    for e in enums {
+        write_stdout_line(&format!("enum {}", e.full_name))?;
+        write_comment_lines(e.comment.as_deref(), "  # ")?;
+        for value in &e.values {
+            write_stdout_line(&format!("  {} = {}", value.name, value.number))?;
+            write_summary_line(value.comment.as_deref(), "    # ")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_field_lines(fields: &[FieldDef], indent: &str) -> Result<()> {
+    for field in fields {
+        let mut line = format!("{indent}{} (", field.json_name);
+        if field.cardinality != "singular" {
+            line.push_str(&field.cardinality);
+            line.push(' ');
+        }
+        line.push_str(&field.kind);
+        line.push(')');
+        if let Some(oneof) = field.oneof.as_ref() {
+            line.push_str(&format!(" [oneof {oneof}]"));
+        }
+        if !field.behaviors.is_empty() {
+            line.push_str(&format!(" [{}]", field.behaviors.join(", ")));
+        }
+        if let Some(pattern) = field.resource_pattern.as_ref() {
+            line.push_str(&format!(" -> {pattern}"));
+        }
+        write_stdout_line(&line)?;
+        write_summary_line(field.comment.as_deref(), &format!("{indent}  # "))?;
+    }
+    Ok(())
+}
+
+fn depth_arg() -> Arg {
+    Arg::new("depth")
+        .long("depth")
+        .value_name("N")
+        .value_parser(value_parser!(usize))
+        .default_value("0")
+        .help("Also describe nested messages and enums up to N levels deep")
+}
+
+fn handle_lint(pool: &prost_reflect::DescriptorPool, matches: &clap::ArgMatches) -> Result<()> {
+    let query = matches
+        .get_one::<String>("query")
+        .ok_or_else(|| anyhow!("--query required"))?;
+    let (parsed, diagnostics) = gaql::lint(pool, query)?;
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == gaql_parser::Severity::Error)
+        .count();
+
+    if matches.get_flag("json") {
+        write_json(
+            &json!({
+                "valid": errors == 0,
+                "diagnostics": diagnostics,
+                "query": parsed,
+            }),
+            true,
+        )?;
+    } else if diagnostics.is_empty() {
+        write_stdout_line("ok")?;
+    } else {
+        for diag in &diagnostics {
+            write_stdout_line(&diag.to_string())?;
+        }
+    }
+
+    if errors > 0 {
+        return Err(anyhow!("query has {errors} error(s)"));
+    }
+    Ok(())
+}
+
+fn doctor_command(name: &'static str) -> Command {
+    Command::new(name)
+        .about("Check credentials, token scopes, accessible customers and login-customer-id")
+        .arg(
+            Arg::new("customer_id")
+                .long("customer-id")
+                .value_name("ID")
+                .help("Customer the login-customer-id must manage (env: GOOGLE_ADS_CUSTOMER_ID)"),
+        )
+        .arg(
+            Arg::new("tokeninfo_url")
+                .long("tokeninfo-url")
+                .value_name("URL")
+                .help("OAuth tokeninfo endpoint (env: GOOGLE_ADS_TOKENINFO_URL)"),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Emit machine-readable JSON"),
+        )
+}
+
+async fn handle_doctor(pool: &prost_reflect::DescriptorPool, matches: &clap::ArgMatches) -> Result<()> {
+    let config = load_config(matches)?;
+    let auth = config.auth_config(!matches.get_flag("no_token_cache"))?;
+    let customer_id = matches
+        .get_one::<String>("customer_id")
+        .map(|value| normalize_customer_id(value));
+    let tokeninfo_url = config
+        .tokeninfo_url
+        .clone()
+        .unwrap_or_else(|| doctor::TOKENINFO_URL.to_string());
+    let report = doctor::run(
+        pool,
+        &config,
+        auth,
+        customer_id,
+        &tokeninfo_url,
+        wire_log(pool, matches)?,
+    )
+    .await?;
+
+    if matches.get_flag("json") {
+        write_json(&serde_json::to_value(&report)?, true)?;
+    } else {
+        for check in &report.checks {
+            write_stdout_line(&format!("[{:>4}] {}: {}", check.status, check.name, check.detail))?;
+        }
+        if !report.accessible_customers.is_empty() {
+            write_stdout_line(&format!(
+                "accessible customers: {}",
+                report.accessible_customers.join(", ")
+            ))?;
+        }
+    }
+    let failures = report.failures();
+    if failures > 0 {
+        return Err(anyhow!("{failures} check(s) failed"));
+    }
+    Ok(())
+}
+
+async fn handle_auth(matches: &clap::ArgMatches) -> Result<()> {
+    if matches.subcommand_matches("logout").is_some() {
+        let config = load_config_with_store(matches, false)?;
+        let stored = StoredCredentials::load().with_context(|| {
+            format!(
+                "could not read stored credentials to revoke them; delete {} to discard them",
+                StoredCredentials::path().map(|p| p.display().to_string()).unwrap_or_default()
+            )
+        })?;
+        let Some(stored) = stored else {
+            eprintln!("no stored credentials");
+            return Ok(());
+        };
+        let token_url = config.token_url.as_deref().unwrap_or(auth::TOKEN_URL);
+        login::logout(config.transport.http_client(), token_url, &stored).await;
+        if let Some(path) = StoredCredentials::delete()? {
+            eprintln!("removed {}", path.display());
+        }
+        return Ok(());
+    }
+
+    let config = load_config(matches)?;
+    let auth = config.auth_config(true)?;
+    let client_id = config
+        .client_id
+        .ok_or_else(|| anyhow!("GOOGLE_ADS_CLIENT_ID missing"))?;
+    let client_secret = config
+        .client_secret
+        .ok_or_else(|| anyhow!("GOOGLE_ADS_CLIENT_SECRET missing"))?;
+    let credentials = login::login(&auth, &client_id, &client_secret).await?;
+    let encrypt = matches
+        .subcommand_matches("login")
+        .is_some_and(|login| login.get_flag("encrypt"));
+    let path = credentials.save(encrypt)?;
+    eprintln!("saved credentials to {}", path.display());
+    Ok(())
+}
+
+fn handle_config_show(matches: &clap::ArgMatches) -> Result<()> {
+    let config = load_config(matches)?;
+    if matches.get_flag("json") {
+        return write_json(
+            &json!({ "profile": config.profile, "settings": config.settings }),
+            true,
+        );
+    }
+    if let Some(profile) = config.profile.as_ref() {
+        write_stdout_line(&format!("profile: {profile}"))?;
+    }
+    let width = config.settings.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    for setting in &config.settings {
+        write_stdout_line(&format!(
+            "{:width$}  {}  ({})",
+            setting.name, setting.value, setting.source
+        ))?;
+    }
+    Ok(())
+}
+
+fn handle_fields_show(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let name = matches
+        .get_one::<String>("name")
+        .ok_or_else(|| anyhow!("name required"))?;
+    let cache = FieldCache::load(&tree.api_version)?
+        .ok_or_else(|| anyhow!("no field cache for {}; run `search-ads fields sync`", tree.api_version))?;
+    let meta = cache.get(name);
+    let children = cache.children(name);
+    if meta.is_none() && children.is_empty() {
+        return Err(anyhow!("unknown field {name}"));
+    }
+
+    if matches.get_flag("json") {
+        let fields = children.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+        return write_json(&json!({ "field": meta, "fields": fields }), true);
+    }
+
+    if let Some(meta) = meta {
+        write_stdout_line(&meta.name)?;
+        write_stdout_line(&format!("  category: {}", meta.category))?;
+        if !meta.data_type.is_empty() {
+            write_stdout_line(&format!("  data_type: {}", meta.data_type))?;
+        }
+        write_stdout_line(&format!(
+            "  selectable: {}  filterable: {}  sortable: {}  repeated: {}",
+            meta.selectable, meta.filterable, meta.sortable, meta.is_repeated
+        ))?;
+        if let Some(type_url) = meta.type_url.as_ref() {
+            write_stdout_line(&format!("  type_url: {type_url}"))?;
+        }
+        if !meta.enum_values.is_empty() {
+            write_stdout_line(&format!("  enum_values: {}", meta.enum_values.join(", ")))?;
+        }
+        if !meta.attribute_resources.is_empty() {
+            write_stdout_line(&format!("  attribute_resources: {}", meta.attribute_resources.join(", ")))?;
+        }
+        if !meta.selectable_with.is_empty() {
+            write_stdout_line(&format!("  selectable_with: {} entries", meta.selectable_with.len()))?;
+            for item in &meta.selectable_with {
+                write_stdout_line(&format!("    {item}"))?;
+            }
+        }
+    }
+    if !children.is_empty() {
+        write_stdout_line("fields:")?;
+        for child in children {
+            write_stdout_line(&format!("  {} ({})", child.name, child.data_type))?;
+        }
     }
     Ok(())
 }
@@ -480,16 +1321,49 @@ fn handle_tree(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
     Ok(())
 }
 
-fn write_gaql_output(output: GaqlOutput, pretty: bool) -> Result<()> {
-    match output {
-        GaqlOutput::Json(value) => write_json(&value, pretty),
-        GaqlOutput::JsonLines(values) => {
-            for value in values {
-                write_stdout_line(&serde_json::to_string(&value)?)?;
-            }
-            Ok(())
-        }
+fn gaql_sink(
+    pool: &prost_reflect::DescriptorPool,
+    matches: &clap::ArgMatches,
+    query: &str,
+    jsonl: bool,
+    pretty: bool,
+    raw: bool,
+    customer_column: bool,
+) -> Result<Box<dyn RowSink>> {
+    let format = matches.get_one::<String>("format").map(String::as_str);
+    let output = matches.get_one::<String>("output").map(std::path::Path::new);
+    let columnar = match format {
+        Some("parquet") => Some(ColumnarFormat::Parquet),
+        Some("arrow") => Some(ColumnarFormat::Arrow),
+        _ => None,
+    };
+    if raw && !matches!(format, None | Some("json") | Some("jsonl")) {
+        return Err(anyhow!("--raw only supports json/jsonl output"));
+    }
+    if let Some(columnar) = columnar {
+        let path = output.ok_or_else(|| anyhow!("--output required for --format parquet/arrow"))?;
+        return Ok(Box::new(ColumnarSink::create(pool, query, customer_column, path, columnar)?));
     }
+
+    let out: Box<dyn Write> = match output {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path).with_context(|| format!("create {}", path.display()))?,
+        )),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout())),
+    };
+    let delimiter = match format {
+        Some("csv") => b',',
+        Some("tsv") => b'\t',
+        Some("json") => return Ok(json_sink(out, false, pretty)),
+        Some("jsonl") => return Ok(json_sink(out, true, pretty)),
+        _ => return Ok(json_sink(out, jsonl, pretty)),
+    };
+    let columns = gaql::table_columns(pool, query, customer_column)?;
+    let list_separator = matches
+        .get_one::<String>("list_separator")
+        .cloned()
+        .unwrap_or_default();
+    Ok(Box::new(CsvSink::new(out, delimiter, columns, list_separator)))
 }
 
 fn write_json(value: &Value, pretty: bool) -> Result<()> {
@@ -508,81 +1382,39 @@ fn write_stdout_line(line: &str) -> Result<()> {
     Ok(())
 }
 
-fn read_customer_id(matches: &clap::ArgMatches) -> Result<String> {
-    let value = matches
+fn read_customer_id(matches: &clap::ArgMatches, config: &config::Config) -> Result<String> {
+    matches
         .get_one::<String>("customer_id")
-        .cloned()
-        .or_else(|| env::var("GOOGLE_ADS_CUSTOMER_ID").ok())
-        .ok_or_else(|| anyhow!("--customer-id or GOOGLE_ADS_CUSTOMER_ID required"))?;
-    Ok(normalize_customer_id(&value))
+        .map(|value| normalize_customer_id(value))
+        .or_else(|| config.customer_id.clone())
+        .ok_or_else(|| anyhow!("--customer-id or GOOGLE_ADS_CUSTOMER_ID required"))
 }
 
-struct Config {
-    developer_token: String,
-    endpoint: String,
-    login_customer_id: Option<String>,
-    access_token: Option<String>,
-    client_id: Option<String>,
-    client_secret: Option<String>,
-    refresh_token: Option<String>,
+fn wire_log(pool: &prost_reflect::DescriptorPool, matches: &clap::ArgMatches) -> Result<Option<WireLog>> {
+    matches
+        .get_one::<String>("log_requests")
+        .map(|path| WireLog::create(pool, std::path::Path::new(path)))
+        .transpose()
 }
 
-fn load_config(matches: &clap::ArgMatches) -> Result<Config> {
-    if matches.get_flag("debug") {
-        env_logger::Builder::from_env("RUST_LOG")
-            .filter_level(log::LevelFilter::Debug)
-            .init();
+fn init_logging(matches: &clap::ArgMatches) {
+    let level = if matches.get_flag("debug") {
+        log::LevelFilter::Debug
     } else {
-        env_logger::Builder::from_env("RUST_LOG")
-            .filter_level(log::LevelFilter::Warn)
-            .init();
-    }
-
-    let developer_token = matches
-        .get_one::<String>("developer_token")
-        .cloned()
-        .or_else(|| env::var("GOOGLE_ADS_DEVELOPER_TOKEN").ok())
-        .ok_or_else(|| anyhow!("GOOGLE_ADS_DEVELOPER_TOKEN missing"))?;
-
-    let access_token = matches
-        .get_one::<String>("access_token")
-        .cloned()
-        .or_else(|| env::var("GOOGLE_ADS_ACCESS_TOKEN").ok());
-
-    let client_id = matches
-        .get_one::<String>("client_id")
-        .cloned()
-        .or_else(|| env::var("GOOGLE_ADS_CLIENT_ID").ok());
-
-    let client_secret = matches
-        .get_one::<String>("client_secret")
-        .cloned()
-        .or_else(|| env::var("GOOGLE_ADS_CLIENT_SECRET").ok());
-
-    let refresh_token = matches
-        .get_one::<String>("refresh_token")
-        .cloned()
-        .or_else(|| env::var("GOOGLE_ADS_REFRESH_TOKEN").ok());
-
-    let login_customer_id = matches
-        .get_one::<String>("login_customer_id")
-        .cloned()
-        .or_else(|| env::var("GOOGLE_ADS_LOGIN_CUSTOMER_ID").ok())
-        .map(|value| normalize_customer_id(&value));
-
-    let endpoint = matches
-        .get_one::<String>("endpoint")
-        .cloned()
-        .or_else(|| env::var("GOOGLE_ADS_ENDPOINT").ok())
-        .unwrap_or_else(|| "https://googleads.googleapis.com".to_string());
-
-    Ok(Config {
-        developer_token,
-        endpoint,
-        login_customer_id,
-        access_token,
-        client_id,
-        client_secret,
-        refresh_token,
-    })
+        log::LevelFilter::Warn
+    };
+    env_logger::Builder::from_env("RUST_LOG")
+        .filter_level(level)
+        .format(|buf, record| {
+            writeln!(
+                buf,
+                "[{} {} {}] {}",
+                buf.timestamp(),
+                record.level(),
+                record.target(),
+                redact::redact(&record.args().to_string())
+            )
+        })
+        .init();
 }
+
@@ -0,0 +1,223 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::fmt;
+use std::io::{BufWriter, Write};
+
+pub trait RowSink {
+    fn write_row(&mut self, row: &Value) -> Result<()>;
+    fn flush(&mut self) -> Result<()>;
+    fn finish(&mut self) -> Result<()>;
+}
+
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("interrupted")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+#[derive(Debug)]
+pub struct PartialFailure {
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl fmt::Display for PartialFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} accounts failed", self.failed, self.total)
+    }
+}
+
+impl std::error::Error for PartialFailure {}
+
+pub fn stdout_json_sink(jsonl: bool, pretty: bool) -> Box<dyn RowSink> {
+    json_sink(BufWriter::new(std::io::stdout()), jsonl, pretty)
+}
+
+pub fn json_sink<W: Write + 'static>(out: W, jsonl: bool, pretty: bool) -> Box<dyn RowSink> {
+    if jsonl {
+        Box::new(JsonSink::lines(out))
+    } else {
+        Box::new(JsonSink::array(out, pretty))
+    }
+}
+
+pub struct JsonSink<W: Write> {
+    out: W,
+    lines: bool,
+    pretty: bool,
+    rows: usize,
+}
+
+impl<W: Write> JsonSink<W> {
+    pub fn array(out: W, pretty: bool) -> Self {
+        Self {
+            out,
+            lines: false,
+            pretty,
+            rows: 0,
+        }
+    }
+
+    pub fn lines(out: W) -> Self {
+        Self {
+            out,
+            lines: true,
+            pretty: false,
+            rows: 0,
+        }
+    }
+}
+
+impl<W: Write> RowSink for JsonSink<W> {
+    fn write_row(&mut self, row: &Value) -> Result<()> {
+        if self.lines {
+            serde_json::to_writer(&mut self.out, row)?;
+            self.out.write_all(b"\n")?;
+            self.rows += 1;
+            return Ok(());
+        }
+
+        let sep: &[u8] = match (self.rows, self.pretty) {
+            (0, true) => b"[\n",
+            (0, false) => b"[",
+            (_, true) => b",\n",
+            (_, false) => b",",
+        };
+        self.out.write_all(sep)?;
+        if self.pretty {
+            let text = serde_json::to_string_pretty(row)?;
+            for (idx, line) in text.lines().enumerate() {
+                if idx > 0 {
+                    self.out.write_all(b"\n")?;
+                }
+                self.out.write_all(b"  ")?;
+                self.out.write_all(line.as_bytes())?;
+            }
+        } else {
+            serde_json::to_writer(&mut self.out, row)?;
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if !self.lines {
+            let close: &[u8] = match (self.rows, self.pretty) {
+                (0, _) => b"[]\n",
+                (_, true) => b"\n]\n",
+                (_, false) => b"]\n",
+            };
+            self.out.write_all(close)?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+pub struct TableColumn {
+    pub name: String,
+    pub path: Vec<String>,
+    pub default: String,
+}
+
+pub struct CsvSink<W: Write> {
+    writer: csv::Writer<W>,
+    columns: Vec<TableColumn>,
+    list_separator: String,
+    header_written: bool,
+}
+
+impl<W: Write> CsvSink<W> {
+    pub fn new(out: W, delimiter: u8, columns: Vec<TableColumn>, list_separator: String) -> Self {
+        let writer = csv::WriterBuilder::new().delimiter(delimiter).from_writer(out);
+        Self {
+            writer,
+            columns,
+            list_separator,
+            header_written: false,
+        }
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        if !self.header_written {
+            self.writer
+                .write_record(self.columns.iter().map(|c| c.name.as_str()))?;
+            self.header_written = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> RowSink for CsvSink<W> {
+    fn write_row(&mut self, row: &Value) -> Result<()> {
+        self.write_header()?;
+        let record = self
+            .columns
+            .iter()
+            .map(|column| flatten_cell(row, column, &self.list_separator))
+            .collect::<Vec<_>>();
+        self.writer.write_record(&record)?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.write_header()?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+pub fn flatten_cell(row: &Value, column: &TableColumn, list_separator: &str) -> String {
+    let mut values = Vec::new();
+    collect_values(row, &column.path, &mut values);
+    if values.is_empty() {
+        return column.default.clone();
+    }
+    values
+        .into_iter()
+        .map(scalar_text)
+        .collect::<Vec<_>>()
+        .join(list_separator)
+}
+
+pub fn collect_values<'a>(value: &'a Value, path: &[String], out: &mut Vec<&'a Value>) {
+    if let Value::Array(items) = value {
+        for item in items {
+            collect_values(item, path, out);
+        }
+        return;
+    }
+    match path.split_first() {
+        None if !value.is_null() => out.push(value),
+        None => {}
+        Some((head, rest)) => {
+            if let Some(next) = value.get(head) {
+                collect_values(next, rest, out);
+            }
+        }
+    }
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        Value::Bool(_) | Value::Number(_) => value.to_string(),
+        Value::Array(_) | Value::Object(_) => serde_json::to_string(value).unwrap_or_default(),
+    }
+}
@@ -0,0 +1,48 @@
+use regex::Regex;
+use std::sync::{LazyLock, RwLock};
+
+pub const MASK: &str = "[REDACTED]";
+const MIN_SECRET_LEN: usize = 8;
+
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+static PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    [
+        r"(?i)(bearer\s+)[A-Za-z0-9._~+/=-]+",
+        r#"(?i)((?:authorization|developer-token|x-goog-api-key)"?\s*[:=]\s*"?)[^",}\s]+"#,
+        r#"(?i)((?:access_token|refresh_token|client_secret|assertion|subject_token|code_verifier)"?\s*[:=]\s*"?)[^",&}\s]+"#,
+        r"(ya29\.)[A-Za-z0-9._-]+",
+        r"(1//)[A-Za-z0-9._-]{10,}",
+        r"(://[^:/@\s]+:)[^@/\s]+(@)",
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).expect("valid redaction pattern"))
+    .collect()
+});
+
+pub fn register(secret: &str) {
+    let secret = secret.trim();
+    if secret.len() < MIN_SECRET_LEN {
+        return;
+    }
+    if let Ok(mut secrets) = SECRETS.write()
+        && !secrets.iter().any(|s| s == secret)
+    {
+        secrets.push(secret.to_string());
+    }
+}
+
+pub fn redact(text: &str) -> String {
+    let mut out = text.to_string();
+    if let Ok(secrets) = SECRETS.read() {
+        for secret in secrets.iter() {
+            if out.contains(secret.as_str()) {
+                out = out.replace(secret.as_str(), MASK);
+            }
+        }
+    }
+    for pattern in PATTERNS.iter() {
+        out = pattern.replace_all(&out, format!("${{1}}{MASK}${{2}}")).into_owned();
+    }
+    out
+}
@@ -0,0 +1,190 @@
+use anyhow::{Context, Result, anyhow};
+use prost_reflect::MethodDescriptor;
+use rand::Rng;
+use serde::Deserialize;
+use std::time::Duration;
+use tonic::{Code, Status};
+
+const SERVICE_CONFIG: &str =
+    include_str!("../schemas/google/ads/googleads/v23/googleads_grpc_service_config.json");
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServiceConfig {
+    #[serde(default)]
+    method_config: Vec<MethodConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MethodConfig {
+    #[serde(default)]
+    name: Vec<MethodName>,
+    timeout: Option<String>,
+    retry_policy: Option<RetryPolicyConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct MethodName {
+    service: Option<String>,
+    method: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RetryPolicyConfig {
+    max_attempts: Option<u32>,
+    initial_backoff: String,
+    max_backoff: String,
+    backoff_multiplier: f64,
+    #[serde(default)]
+    retryable_status_codes: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    pub retryable: Vec<Code>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MethodPolicy {
+    pub timeout: Option<Duration>,
+    pub retry: Option<RetryPolicy>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServicePolicies {
+    entries: Vec<(MethodName, MethodPolicy)>,
+    max_attempts: Option<u32>,
+}
+
+impl ServicePolicies {
+    pub fn load() -> Result<Self> {
+        Self::parse(SERVICE_CONFIG)
+    }
+
+    fn parse(raw: &str) -> Result<Self> {
+        let config: ServiceConfig =
+            serde_json::from_str(raw).context("decode grpc service config")?;
+        let mut entries = Vec::new();
+        for method_config in config.method_config {
+            let policy = MethodPolicy {
+                timeout: method_config
+                    .timeout
+                    .as_deref()
+                    .map(parse_duration)
+                    .transpose()?,
+                retry: method_config
+                    .retry_policy
+                    .map(retry_policy)
+                    .transpose()?,
+            };
+            for name in method_config.name {
+                entries.push((name, policy.clone()));
+            }
+        }
+        Ok(Self {
+            entries,
+            max_attempts: None,
+        })
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: Option<u32>) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn for_method(&self, method: &MethodDescriptor) -> MethodPolicy {
+        let service = method.parent_service().full_name().to_string();
+        let exact = self.entries.iter().find(|(name, _)| {
+            name.service.as_deref() == Some(service.as_str())
+                && name.method.as_deref() == Some(method.name())
+        });
+        let by_service = || {
+            self.entries.iter().find(|(name, _)| {
+                name.service.as_deref() == Some(service.as_str()) && name.method.is_none()
+            })
+        };
+
+        let mut policy = exact
+            .or_else(by_service)
+            .map(|(_, policy)| policy.clone())
+            .unwrap_or_default();
+        if let (Some(retry), Some(max_attempts)) = (policy.retry.as_mut(), self.max_attempts) {
+            retry.max_attempts = max_attempts.max(1);
+        }
+        policy
+    }
+}
+
+impl MethodPolicy {
+    pub fn retry_delay(&self, status: &Status, attempt: u32) -> Option<Duration> {
+        let retry = self.retry.as_ref()?;
+        if attempt >= retry.max_attempts || !retry.retryable.contains(&status.code()) {
+            return None;
+        }
+        Some(retry.backoff(attempt))
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let ceiling = (self.initial_backoff.as_secs_f64() * exp).min(self.max_backoff.as_secs_f64());
+        let jittered = rand::thread_rng().gen_range(0.0..=ceiling);
+        Duration::from_secs_f64(jittered)
+    }
+}
+
+fn retry_policy(config: RetryPolicyConfig) -> Result<RetryPolicy> {
+    let retryable = config
+        .retryable_status_codes
+        .iter()
+        .map(|code| parse_code(code))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(RetryPolicy {
+        max_attempts: config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS),
+        initial_backoff: parse_duration(&config.initial_backoff)?,
+        max_backoff: parse_duration(&config.max_backoff)?,
+        multiplier: config.backoff_multiplier,
+        retryable,
+    })
+}
+
+fn parse_duration(value: &str) -> Result<Duration> {
+    let secs = value
+        .strip_suffix('s')
+        .ok_or_else(|| anyhow!("invalid duration {value}"))?;
+    let secs: f64 = secs
+        .parse()
+        .map_err(|_| anyhow!("invalid duration {value}"))?;
+    Ok(Duration::from_secs_f64(secs))
+}
+
+fn parse_code(value: &str) -> Result<Code> {
+    let code = match value {
+        "CANCELLED" => Code::Cancelled,
+        "UNKNOWN" => Code::Unknown,
+        "INVALID_ARGUMENT" => Code::InvalidArgument,
+        "DEADLINE_EXCEEDED" => Code::DeadlineExceeded,
+        "NOT_FOUND" => Code::NotFound,
+        "ALREADY_EXISTS" => Code::AlreadyExists,
+        "PERMISSION_DENIED" => Code::PermissionDenied,
+        "RESOURCE_EXHAUSTED" => Code::ResourceExhausted,
+        "FAILED_PRECONDITION" => Code::FailedPrecondition,
+        "ABORTED" => Code::Aborted,
+        "OUT_OF_RANGE" => Code::OutOfRange,
+        "UNIMPLEMENTED" => Code::Unimplemented,
+        "INTERNAL" => Code::Internal,
+        "UNAVAILABLE" => Code::Unavailable,
+        "DATA_LOSS" => Code::DataLoss,
+        "UNAUTHENTICATED" => Code::Unauthenticated,
+        other => return Err(anyhow!("unknown status code {other}")),
+    };
+    Ok(code)
+}
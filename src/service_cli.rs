@@ -0,0 +1,195 @@
+use anyhow::{Result, anyhow};
+use clap::builder::PossibleValuesParser;
+use clap::{Arg, ArgAction, ArgMatches, Command, value_parser};
+use prost_reflect::{DescriptorPool, FieldDescriptor, Kind, MethodDescriptor};
+use serde_json::{Map, Value};
+
+use crate::auth::normalize_customer_id;
+use crate::command_tree::{CommandTree, find_method, to_kebab};
+use crate::docs;
+use crate::json_input::read_json_input;
+
+const BODY_ARG: &str = "body";
+const CUSTOMER_ID_FIELD: &str = "customer_id";
+
+pub fn service_commands(pool: &DescriptorPool, tree: &CommandTree, reserved: &[String]) -> Vec<Command> {
+    let mut commands = Vec::new();
+    for service in &tree.services {
+        let mut cmd = with_docs(Command::new(service.name.clone()), &service.full_name, service.comment.as_deref())
+            .subcommand_required(true);
+        for method in &service.methods {
+            if method.client_streaming {
+                continue;
+            }
+            let Ok(desc) = find_method(pool, &service.name, &method.name) else {
+                continue;
+            };
+            cmd = cmd.subcommand(method_command(&desc, &method.name, reserved));
+        }
+        commands.push(cmd);
+    }
+    commands
+}
+
+fn method_command(method: &MethodDescriptor, name: &str, reserved: &[String]) -> Command {
+    let about = if method.is_server_streaming() {
+        format!("{} (server streaming)", method.full_name())
+    } else {
+        method.full_name().to_string()
+    };
+    let mut cmd = with_docs(Command::new(name.to_string()), &about, docs::comment(method.full_name()))
+        .next_help_heading("Request")
+        .arg(
+            Arg::new(BODY_ARG)
+                .long("body")
+                .value_name("JSON")
+                .help("Request body JSON (or @file); flags override its fields"),
+        );
+    for field in method.input().fields() {
+        if field.name() == BODY_ARG || reserved.iter().any(|r| r == field.name()) {
+            continue;
+        }
+        cmd = cmd.arg(field_docs(field_arg(&field), &field));
+    }
+    cmd
+}
+
+fn with_docs(cmd: Command, name: &str, comment: Option<&str>) -> Command {
+    match comment {
+        Some(comment) => cmd
+            .about(docs::summary(comment))
+            .long_about(format!("{name}\n\n{comment}")),
+        None => cmd.about(name.to_string()),
+    }
+}
+
+fn field_docs(arg: Arg, field: &FieldDescriptor) -> Arg {
+    let Some(comment) = docs::comment(field.full_name()) else {
+        return arg;
+    };
+    let kind = arg.get_help().map(ToString::to_string).unwrap_or_default();
+    arg.help(docs::summary(comment))
+        .long_help(format!("{comment}\n\n{kind}"))
+}
+
+fn field_arg(field: &FieldDescriptor) -> Arg {
+    let arg = Arg::new(field.name().to_string()).long(to_kebab(field.name()));
+    let repeated = field.is_list();
+    match field.kind() {
+        _ if field.is_map() => arg.value_name("JSON").help("Map as a JSON object (or @file)"),
+        Kind::Message(message) if repeated => arg
+            .value_name("JSON")
+            .help(format!("List of {} as a JSON array (or @file)", message.full_name())),
+        Kind::Message(message) => arg
+            .value_name("JSON")
+            .help(format!("{} as JSON (or @file)", message.full_name())),
+        Kind::Bool if !repeated => arg.action(ArgAction::SetTrue).help("bool"),
+        Kind::Enum(e) => {
+            let names = e.values().map(|v| v.name().to_string()).collect::<Vec<_>>();
+            scalar_action(arg, repeated)
+                .value_name("ENUM")
+                .value_parser(PossibleValuesParser::new(names))
+                .help(format!("{}{}", e.full_name(), repeated_suffix(repeated)))
+        }
+        kind => {
+            let name = format!("{kind:?}").to_lowercase();
+            let arg = match kind {
+                Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => arg.value_parser(value_parser!(i32)),
+                Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => arg.value_parser(value_parser!(i64)),
+                Kind::Uint32 | Kind::Fixed32 => arg.value_parser(value_parser!(u32)),
+                Kind::Uint64 | Kind::Fixed64 => arg.value_parser(value_parser!(u64)),
+                Kind::Float => arg.value_parser(value_parser!(f32)),
+                Kind::Double => arg.value_parser(value_parser!(f64)),
+                Kind::Bool => arg.value_parser(value_parser!(bool)),
+                _ => arg,
+            };
+            scalar_action(arg, repeated)
+                .value_name(name.to_uppercase())
+                .help(format!("{name}{}", repeated_suffix(repeated)))
+        }
+    }
+}
+
+fn scalar_action(arg: Arg, repeated: bool) -> Arg {
+    if repeated { arg.action(ArgAction::Append) } else { arg }
+}
+
+fn repeated_suffix(repeated: bool) -> &'static str {
+    if repeated { " (repeatable)" } else { "" }
+}
+
+pub fn request_body(method: &MethodDescriptor, matches: &ArgMatches, customer_id: Option<&str>) -> Result<Value> {
+    let mut body = match matches.get_one::<String>(BODY_ARG) {
+        Some(raw) => match read_json_input(raw)? {
+            Value::Object(map) => map,
+            _ => return Err(anyhow!("--body must be a JSON object")),
+        },
+        None => Map::new(),
+    };
+    for field in method.input().fields() {
+        if let Some(value) = field_value(&field, matches)? {
+            body.insert(field.json_name().to_string(), value);
+        }
+    }
+    if let Some(customer_id) = customer_id
+        && let Some(field) = method.input().get_field_by_name(CUSTOMER_ID_FIELD)
+        && !body.contains_key(field.json_name())
+        && !body.contains_key(CUSTOMER_ID_FIELD)
+    {
+        body.insert(field.json_name().to_string(), Value::String(customer_id.to_string()));
+    }
+    Ok(Value::Object(body))
+}
+
+fn field_value(field: &FieldDescriptor, matches: &ArgMatches) -> Result<Option<Value>> {
+    let id = field.name();
+    if !matches.try_contains_id(id).unwrap_or(false)
+        || matches.value_source(id) != Some(clap::parser::ValueSource::CommandLine)
+    {
+        return Ok(None);
+    }
+    if field.is_map() || matches!(field.kind(), Kind::Message(_)) {
+        return matches.get_one::<String>(id).map(|raw| read_json_input(raw)).transpose();
+    }
+    if matches!(field.kind(), Kind::Bool) && !field.is_list() {
+        return Ok(Some(Value::Bool(matches.get_flag(id))));
+    }
+    let values: Vec<Value> = match field.kind() {
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => collect::<i32>(matches, id),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => collect::<i64>(matches, id),
+        Kind::Uint32 | Kind::Fixed32 => collect::<u32>(matches, id),
+        Kind::Uint64 | Kind::Fixed64 => collect::<u64>(matches, id),
+        Kind::Float => collect::<f32>(matches, id),
+        Kind::Double => collect::<f64>(matches, id),
+        Kind::Bool => collect::<bool>(matches, id),
+        _ => matches
+            .get_many::<String>(id)
+            .into_iter()
+            .flatten()
+            .map(|value| {
+                if id == CUSTOMER_ID_FIELD {
+                    Value::String(normalize_customer_id(value))
+                } else {
+                    Value::String(value.clone())
+                }
+            })
+            .collect(),
+    };
+    if field.is_list() {
+        return Ok(Some(Value::Array(values)));
+    }
+    Ok(values.into_iter().next())
+}
+
+fn collect<T>(matches: &ArgMatches, id: &str) -> Vec<Value>
+where
+    T: Clone + Send + Sync + Into<Value> + 'static,
+{
+    matches
+        .get_many::<T>(id)
+        .into_iter()
+        .flatten()
+        .cloned()
+        .map(Into::into)
+        .collect()
+}
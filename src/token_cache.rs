@@ -0,0 +1,98 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::auth::AccessToken;
+use crate::paths::{cache_dir, create_private_dir, write_private};
+use crate::redact;
+
+pub const REFRESH_MARGIN_SECS: u64 = 300;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedToken {
+    access_token: String,
+    expires_at: u64,
+}
+
+pub struct TokenCache {
+    path: PathBuf,
+    reuse: bool,
+}
+
+impl TokenCache {
+    pub fn new(token_url: &str, principal: &str, secret: &str) -> Result<Self> {
+        let mut hasher = Sha256::new();
+        hasher.update(token_url.as_bytes());
+        hasher.update([0]);
+        hasher.update(secret.as_bytes());
+        let digest = hasher.finalize();
+        let hash: String = digest[..12].iter().map(|b| format!("{b:02x}")).collect();
+        let client: String = principal
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+            .collect();
+        let path = cache_dir()?
+            .join("tokens")
+            .join(format!("{client}-{hash}.json"));
+        Ok(Self { path, reuse: true })
+    }
+
+    pub fn reuse(mut self, reuse: bool) -> Self {
+        self.reuse = reuse;
+        self
+    }
+
+    pub fn load(&self) -> Option<AccessToken> {
+        if !self.reuse {
+            return None;
+        }
+        let contents = fs::read_to_string(&self.path).ok()?;
+        let cached: CachedToken = match serde_json::from_str(&contents) {
+            Ok(cached) => cached,
+            Err(err) => {
+                log::debug!("ignoring unreadable token cache {}: {err}", self.path.display());
+                return None;
+            }
+        };
+        if cached.expires_at <= now() + REFRESH_MARGIN_SECS {
+            log::debug!("cached access token expired or near expiry");
+            return None;
+        }
+        log::debug!("using cached access token from {}", self.path.display());
+        redact::register(&cached.access_token);
+        Some(AccessToken {
+            token: cached.access_token,
+            expires_at: Some(cached.expires_at),
+        })
+    }
+
+    pub fn store(&self, access_token: &str, expires_at: u64) -> Result<()> {
+        let cached = CachedToken {
+            access_token: access_token.to_string(),
+            expires_at,
+        };
+        if let Some(parent) = self.path.parent() {
+            create_private_dir(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        write_private(&tmp, &serde_json::to_vec(&cached)?)
+            .with_context(|| format!("write {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path).with_context(|| format!("write {}", self.path.display()))?;
+        Ok(())
+    }
+
+    pub fn clear(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,108 @@
+use anyhow::{Context, Result, anyhow};
+use reqwest::Url;
+use std::fs;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity};
+
+use crate::proxy::{self, ProxyConnector};
+
+#[derive(Debug, Clone, Default)]
+pub struct TransportSettings {
+    pub ca_bundle: Option<String>,
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    pub authority: Option<String>,
+    pub proxy: Option<String>,
+}
+
+impl TransportSettings {
+    pub fn configure_endpoint(&self, endpoint: &str) -> Result<Endpoint> {
+        let mut builder = Endpoint::from_shared(endpoint.to_string())?;
+        let tls = endpoint.starts_with("https://");
+        if let Some(authority) = self.authority.as_ref() {
+            let scheme = if tls { "https" } else { "http" };
+            let origin = format!("{scheme}://{authority}")
+                .parse()
+                .map_err(|err| anyhow!("invalid authority {authority}: {err}"))?;
+            builder = builder.origin(origin);
+        }
+        if tls {
+            builder = builder
+                .tls_config(self.grpc_tls()?)
+                .map_err(|err| anyhow!("tls config error: {err}"))?;
+        }
+        Ok(builder)
+    }
+
+    pub async fn connect(&self, builder: Endpoint) -> Result<Channel> {
+        let target = Url::parse(&builder.uri().to_string())?;
+        let Some(proxy) = proxy::resolve(self.proxy.as_deref(), &target)? else {
+            return Ok(builder.connect().await?);
+        };
+        log::debug!("using proxy {} for {target}", proxy.url());
+        Ok(builder
+            .connect_with_connector(ProxyConnector::new(proxy))
+            .await?)
+    }
+
+    pub fn http_client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(value) = self.proxy.as_ref() {
+            let proxy = proxy::Proxy::parse(value)?;
+            builder = builder.proxy(
+                reqwest::Proxy::all(proxy.url())
+                    .context("configure proxy")?
+                    .no_proxy(reqwest::NoProxy::from_env()),
+            );
+        }
+        if let Some(path) = self.ca_bundle.as_ref() {
+            for cert in reqwest::Certificate::from_pem_bundle(&read(path, "CA bundle")?)
+                .with_context(|| format!("parse CA bundle {path}"))?
+            {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let Some((cert, key)) = self.client_identity()? {
+            let mut pem = cert;
+            pem.push(b'\n');
+            pem.extend(key);
+            let identity = reqwest::Identity::from_pem(&pem).context("parse client certificate")?;
+            builder = builder.identity(identity);
+        }
+        builder.build().context("build http client")
+    }
+
+    fn grpc_tls(&self) -> Result<ClientTlsConfig> {
+        let mut tls = ClientTlsConfig::new().with_webpki_roots();
+        if let Some(path) = self.ca_bundle.as_ref() {
+            tls = tls.ca_certificate(Certificate::from_pem(read(path, "CA bundle")?));
+        }
+        if let Some((cert, key)) = self.client_identity()? {
+            tls = tls.identity(Identity::from_pem(cert, key));
+        }
+        if let Some(authority) = self.authority.as_ref() {
+            let host = authority
+                .rsplit_once(':')
+                .filter(|(_, port)| port.chars().all(|c| c.is_ascii_digit()))
+                .map_or(authority.as_str(), |(host, _)| host);
+            tls = tls.domain_name(host);
+        }
+        Ok(tls)
+    }
+
+    fn client_identity(&self) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        match (self.client_cert.as_ref(), self.client_key.as_ref()) {
+            (Some(cert), Some(key)) => Ok(Some((read(cert, "client certificate")?, read(key, "client key")?))),
+            (None, None) => Ok(None),
+            (Some(_), None) => Err(anyhow!(
+                "client certificate set without a key; set --client-key, GOOGLE_ADS_CLIENT_KEY or client_key"
+            )),
+            (None, Some(_)) => Err(anyhow!(
+                "client key set without a certificate; set --client-cert, GOOGLE_ADS_CLIENT_CERT or client_cert"
+            )),
+        }
+    }
+}
+
+fn read(path: &str, what: &str) -> Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("read {what} {path}"))
+}
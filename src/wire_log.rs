@@ -0,0 +1,188 @@
+use anyhow::{Context, Result};
+use prost_reflect::{DescriptorPool, DynamicMessage, MethodDescriptor};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Request, Response, Status, Streaming};
+
+use crate::errors::{code_name, status_report};
+use crate::paths::append_private;
+use crate::proto_json::dynamic_to_value;
+use crate::redact::MASK;
+
+const SECRET_HEADERS: &[&str] = &["authorization", "developer-token"];
+
+pub struct WireLog {
+    pool: DescriptorPool,
+    out: Mutex<File>,
+}
+
+#[derive(Serialize)]
+pub struct Entry {
+    timestamp_ms: u128,
+    method: String,
+    customer_id: Option<String>,
+    attempt: u32,
+    request_headers: BTreeMap<String, String>,
+    request: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    streamed_messages: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<Value>,
+    status: &'static str,
+    request_id: Option<String>,
+    latency_ms: f64,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+impl WireLog {
+    pub fn create(pool: &DescriptorPool, path: &Path) -> Result<Self> {
+        let file = append_private(path).with_context(|| format!("open request log {}", path.display()))?;
+        Ok(Self {
+            pool: pool.clone(),
+            out: Mutex::new(file),
+        })
+    }
+
+    pub fn start(&self, method: &MethodDescriptor, request: &Request<DynamicMessage>, attempt: u32) -> Entry {
+        let message = request.get_ref();
+        let customer_id = message
+            .get_field_by_name("customer_id")
+            .and_then(|v| v.as_str().map(str::to_string))
+            .filter(|v| !v.is_empty());
+        Entry {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or_default(),
+            method: format!("/{}/{}", method.parent_service().full_name(), method.name()),
+            customer_id,
+            attempt,
+            request_headers: headers(request.metadata()),
+            request: dynamic_to_value(message).unwrap_or(Value::Null),
+            response: None,
+            streamed_messages: None,
+            error: None,
+            status: code_name(Code::Ok),
+            request_id: None,
+            latency_ms: 0.0,
+            started: Some(Instant::now()),
+        }
+    }
+
+    pub fn success(&self, mut entry: Entry, metadata: &MetadataMap, response: Option<&DynamicMessage>) {
+        entry.request_id = request_id(metadata);
+        entry.response = response.map(|r| dynamic_to_value(r).unwrap_or(Value::Null));
+        self.write(entry);
+    }
+
+    pub fn failure(&self, mut entry: Entry, status: &Status) {
+        let report = status_report(&self.pool, status);
+        entry.status = code_name(status.code());
+        entry.request_id = entry.request_id.take().or(report.request_id.clone());
+        entry.error = Some(report.to_json());
+        self.write(entry);
+    }
+
+    fn write(&self, mut entry: Entry) {
+        if let Some(started) = entry.started.take() {
+            entry.latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+        }
+        let Ok(mut line) = serde_json::to_vec(&entry) else {
+            return;
+        };
+        line.push(b'\n');
+        if let Ok(mut out) = self.out.lock()
+            && let Err(err) = out.write_all(&line)
+        {
+            log::warn!("failed to write request log: {err}");
+        }
+    }
+}
+
+pub struct ResponseStream {
+    inner: Streaming<DynamicMessage>,
+    log: Option<(Arc<WireLog>, Entry)>,
+    messages: u64,
+}
+
+impl ResponseStream {
+    pub fn new(response: Response<Streaming<DynamicMessage>>, mut log: Option<(Arc<WireLog>, Entry)>) -> Self {
+        if let Some((_, entry)) = log.as_mut() {
+            entry.request_id = request_id(response.metadata());
+        }
+        Self {
+            inner: response.into_inner(),
+            log,
+            messages: 0,
+        }
+    }
+
+    pub async fn message(&mut self) -> Result<Option<DynamicMessage>, Status> {
+        match self.inner.message().await {
+            Ok(Some(message)) => {
+                self.messages += 1;
+                Ok(Some(message))
+            }
+            Ok(None) => {
+                self.finish(None);
+                Ok(None)
+            }
+            Err(status) => {
+                self.finish(Some(&status));
+                Err(status)
+            }
+        }
+    }
+
+    fn finish(&mut self, status: Option<&Status>) {
+        let Some((log, mut entry)) = self.log.take() else {
+            return;
+        };
+        entry.streamed_messages = Some(self.messages);
+        match status {
+            Some(status) => log.failure(entry, status),
+            None => log.write(entry),
+        }
+    }
+}
+
+impl Drop for ResponseStream {
+    fn drop(&mut self) {
+        if self.log.is_some() {
+            self.finish(Some(&Status::cancelled("stream dropped before completion")));
+        }
+    }
+}
+
+fn headers(metadata: &MetadataMap) -> BTreeMap<String, String> {
+    metadata
+        .clone()
+        .into_headers()
+        .iter()
+        .map(|(name, value)| {
+            let value = if SECRET_HEADERS.contains(&name.as_str()) {
+                MASK.to_string()
+            } else {
+                value.to_str().unwrap_or_default().to_string()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+fn request_id(metadata: &MetadataMap) -> Option<String> {
+    metadata
+        .get("request-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
@@ -0,0 +1,97 @@
+#!/usr/bin/env python3
+"""Extract leading proto comments into a gzipped `full.name<TAB>comment` index."""
+import argparse
+import gzip
+import re
+from pathlib import Path
+
+SCOPE_RE = re.compile(r"^(message|enum|service|oneof)\s+(\w+)\s*\{")
+RPC_RE = re.compile(r"^rpc\s+(\w+)\s*\(")
+FIELD_RE = re.compile(r"^(?:optional\s+|repeated\s+)?(?:map<[^>]+>|[\w.]+)\s+(\w+)\s*=\s*\d+")
+ENUM_VALUE_RE = re.compile(r"^(\w+)\s*=\s*-?\d+")
+PACKAGE_RE = re.compile(r"^package\s+([\w.]+)\s*;")
+STRING_RE = re.compile(r'"(?:[^"\\]|\\.)*"')
+XREF_RE = re.compile(r"\[([^\]]+)\]\[[\w.]*\]")
+THROWN_ERRORS_RE = re.compile(r"List of thrown errors:(?:\n\s*\[\w+\]\(\))*")
+
+
+def scope_name(package, scopes):
+    names = [name for kind, name in scopes if kind in ("message", "enum", "service")]
+    return ".".join([package, *names]) if package else ".".join(names)
+
+
+def parse(path, docs):
+    package = ""
+    scopes = []
+    pending = []
+    statement = ""
+    comment = ""
+    for raw in path.read_text(encoding="utf-8").splitlines():
+        line = raw.strip()
+        if line.startswith("//"):
+            if not statement:
+                pending.append(line[2:][1:] if line[2:].startswith(" ") else line[2:])
+            continue
+        if not line:
+            if not statement:
+                pending = []
+            continue
+
+        if not statement:
+            comment = THROWN_ERRORS_RE.sub("", XREF_RE.sub(r"\1", "\n".join(pending))).strip()
+            pending = []
+        code = STRING_RE.sub('""', line).split("//", 1)[0].strip()
+        statement = f"{statement} {code}".strip()
+        if not any(ch in statement for ch in ";{}"):
+            continue
+        code, statement = statement, ""
+
+        top = scopes[-1][0] if scopes else None
+        opened = None
+        if m := PACKAGE_RE.match(code):
+            package = m.group(1)
+        elif m := SCOPE_RE.match(code):
+            kind, name = m.groups()
+            opened = (kind, name)
+            if kind != "oneof" and comment:
+                docs[scope_name(package, scopes + [opened])] = comment
+        elif m := RPC_RE.match(code):
+            if top == "service" and comment:
+                docs[f"{scope_name(package, scopes)}.{m.group(1)}"] = comment
+        elif top in ("message", "oneof") and not code.startswith(("option", "reserved", "extensions")):
+            if (m := FIELD_RE.match(code)) and comment:
+                docs[f"{scope_name(package, scopes)}.{m.group(1)}"] = comment
+        elif top == "enum" and not code.startswith(("option", "reserved")):
+            if (m := ENUM_VALUE_RE.match(code)) and comment:
+                docs[f"{scope_name(package, scopes)}.{m.group(1)}"] = comment
+
+        first_brace = code.find("{")
+        for idx, ch in enumerate(code):
+            if ch == "{":
+                scopes.append(opened if opened and idx == first_brace else ("block", ""))
+            elif ch == "}" and scopes:
+                scopes.pop()
+
+
+def main():
+    root = Path(__file__).resolve().parent.parent
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--protos", default=str(root / "schemas"), help="proto root")
+    parser.add_argument("--out", default=str(root / "schemas" / "googleads.docs.gz"))
+    args = parser.parse_args()
+
+    docs = {}
+    for path in sorted(Path(args.protos).rglob("*.proto")):
+        parse(path, docs)
+
+    lines = []
+    for name in sorted(docs):
+        text = docs[name].replace("\\", "\\\\").replace("\t", " ").replace("\n", "\\n")
+        lines.append(f"{name}\t{text}\n")
+    with gzip.GzipFile(args.out, "wb", mtime=0) as out:
+        out.write("".join(lines).encode("utf-8"))
+    print(f"wrote: {args.out} ({len(lines)} entries)")
+
+
+if __name__ == "__main__":
+    main()